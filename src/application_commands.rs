@@ -1,14 +1,24 @@
 use crate::commands::manage::*;
 use crate::commands::misc::ping::command as pingcommand;
+use crate::commands::music::back;
+use crate::commands::music::chapter;
+use crate::commands::music::favorites;
+use crate::commands::music::grab;
+use crate::commands::music::history;
 use crate::commands::music::join;
 use crate::commands::music::leave;
+use crate::commands::music::like;
+use crate::commands::music::lyrics;
 use crate::commands::music::nowplaying;
 use crate::commands::music::play;
 use crate::commands::music::queue;
 use crate::commands::music::skip;
+use crate::commands::music::stats;
+use crate::commands::music::unlike;
 use crate::commands::music::volume;
-use mongodb::Client;
+use crate::mongo_conn::Db;
 use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::application::interaction::autocomplete::AutocompleteInteraction;
 use serenity::model::application::interaction::message_component::MessageComponentInteraction;
 use serenity::model::prelude::command::Command;
 use serenity::model::prelude::command::CommandPermissionType;
@@ -25,12 +35,26 @@ pub async fn register(ctx: &Context) {
     // If a command fails to register it will panic.
     info!("Registering commands...");
     setmodrole::register(ctx).await;
+    always_on::register(ctx).await;
+    announce::register(ctx).await;
+    cache::register(ctx).await;
+    idletimeout::register(ctx).await;
+    sponsorblock::register(ctx).await;
+    back::register(ctx).await;
+    chapter::register(ctx).await;
+    favorites::register(ctx).await;
+    grab::register(ctx).await;
+    history::register(ctx).await;
     join::register(ctx).await;
     leave::register(ctx).await;
+    like::register(ctx).await;
+    lyrics::register(ctx).await;
     nowplaying::register(ctx).await;
     play::register(ctx).await;
     queue::register(ctx).await;
     skip::register(ctx).await;
+    stats::register(ctx).await;
+    unlike::register(ctx).await;
     volume::register(ctx).await;
     info!("Done.");
 
@@ -53,15 +77,18 @@ pub async fn register(ctx: &Context) {
 pub async fn handle_interactions(
     ctx: &Context,
     intn: Interaction,
-    mongo_client: &mongodb::Client,
+    db: &Db,
 ) {
     match intn {
         Interaction::Ping(_) => {}
         Interaction::ApplicationCommand(a_command) => {
-            handle_commands(ctx, &a_command, mongo_client).await;
+            handle_commands(ctx, &a_command, db).await;
         }
         Interaction::MessageComponent(m_component) => {
-            handle_components(&ctx, &m_component, mongo_client).await;
+            handle_components(ctx, &m_component, db).await;
+        }
+        Interaction::Autocomplete(autocomplete) => {
+            handle_autocomplete(ctx, &autocomplete, db).await;
         }
         _ => {}
     }
@@ -70,7 +97,7 @@ pub async fn handle_interactions(
 async fn handle_commands(
     ctx: &Context,
     interaction: &ApplicationCommandInteraction,
-    mongo_client: &mongodb::Client,
+    db: &Db,
 ) {
     info!(
         "Application command '{}'({}) invoked by user '{}'({}) in Ch.{} Gld.{}",
@@ -84,31 +111,73 @@ async fn handle_commands(
 
     match interaction.data.name.as_str() {
         "pingus" => {
-            pingcommand(ctx, interaction, mongo_client).await;
+            pingcommand(ctx, interaction, db).await;
+        }
+        "247" => {
+            always_on::command(ctx, interaction, db).await;
+        }
+        "announce" => {
+            announce::command(ctx, interaction, db).await;
+        }
+        "cache" => {
+            cache::command(ctx, interaction, db).await;
+        }
+        "back" => {
+            back::command(ctx, interaction, db).await;
+        }
+        "chapter" => {
+            chapter::command(ctx, interaction, db).await;
         }
         "setmodrole" => {
-            setmodrole::command(ctx, interaction, mongo_client).await;
+            setmodrole::command(ctx, interaction, db).await;
+        }
+        "favorites" => {
+            favorites::command(ctx, interaction, db).await;
+        }
+        "grab" => {
+            grab::command(ctx, interaction, db).await;
+        }
+        "history" => {
+            history::command(ctx, interaction, db).await;
+        }
+        "idletimeout" => {
+            idletimeout::command(ctx, interaction, db).await;
         }
         "join" => {
-            join::command(ctx, interaction, mongo_client).await;
+            join::command(ctx, interaction, db).await;
         }
         "leave" => {
-            leave::command(ctx, interaction, mongo_client).await;
+            leave::command(ctx, interaction, db).await;
+        }
+        "like" => {
+            like::command(ctx, interaction, db).await;
+        }
+        "lyrics" => {
+            lyrics::command(ctx, interaction, db).await;
         }
         "nowplaying" => {
-            nowplaying::command(ctx, interaction, mongo_client).await;
+            nowplaying::command(ctx, interaction, db).await;
         }
         "play" => {
-            play::command(ctx, interaction, mongo_client).await;
+            play::command(ctx, interaction, db).await;
         }
         "queue" => {
-            queue::command(ctx, interaction, mongo_client).await;
+            queue::command(ctx, interaction, db).await;
         } 
         "skip" => {
-            skip::command(ctx, interaction, mongo_client).await;
+            skip::command(ctx, interaction, db).await;
+        }
+        "sponsorblock" => {
+            sponsorblock::command(ctx, interaction, db).await;
+        }
+        "stats" => {
+            stats::command(ctx, interaction, db).await;
+        }
+        "unlike" => {
+            unlike::command(ctx, interaction, db).await;
         }
         "volume" => {
-            volume::command(ctx, interaction, mongo_client).await;
+            volume::command(ctx, interaction, db).await;
         }
         _ => {
             warn!("Command not found.");
@@ -117,18 +186,47 @@ async fn handle_commands(
 }
 
 async fn handle_components(
-    _ctx: &&Context,
+    ctx: &Context,
     m_component: &MessageComponentInteraction,
-    _mongo_client: &Client,
+    db: &Db,
 ) {
     let ids_split: Vec<&str> = m_component.data.custom_id.split(':').collect();
-    let _comp_type: &str = match ids_split.first() {
+    let comp_type: &str = match ids_split.first() {
         Some(str_type) => str_type,
         None => "none",
     };
     // TODO possibly avoid another split here by using this split again, but for now I dont want to edit the signiture
-    {
-        warn!("Interaction not found.");
+    match comp_type {
+        "grab" => {
+            grab::component(ctx, m_component, db).await;
+        }
+        "history" => {
+            history::component(ctx, m_component, db).await;
+        }
+        "like" => {
+            like::component(ctx, m_component, db).await;
+        }
+        "lyrics" => {
+            lyrics::component(ctx, m_component, db).await;
+        }
+        _ => {
+            warn!("Interaction not found.");
+        }
+    }
+}
+
+async fn handle_autocomplete(
+    ctx: &Context,
+    autocomplete: &AutocompleteInteraction,
+    db: &Db,
+) {
+    match autocomplete.data.name.as_str() {
+        "play" => {
+            play::autocomplete(ctx, autocomplete, db).await;
+        }
+        _ => {
+            warn!("Autocomplete not found.");
+        }
     }
 }
 
@@ -172,6 +270,7 @@ async fn handle_components(
 //     info!("Commands cleared. Will now re-add commands.");
 // }
 
+#[allow(dead_code)]
 #[instrument(skip(ctx, command))]
 pub async fn add_admins_to_perms(
     ctx: &Context,
@@ -226,6 +325,7 @@ pub async fn add_admins_to_perms(
     Ok(())
 }
 
+#[allow(dead_code)]
 #[instrument(skip(ctx))]
 pub async fn get_vec_of_perms(
     ctx: &Context,
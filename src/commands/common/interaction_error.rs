@@ -67,7 +67,6 @@ pub async fn interaction_error_edit(
     }
 }
 
-#[allow(dead_code)]
 pub async fn interaction_error_comp(
     err_message: &str,
     command: &MessageComponentInteraction,
@@ -1,3 +1,4 @@
 pub mod interaction_error;
 pub mod permissions_check;
+pub mod player_buttons;
 pub mod slash_commands;
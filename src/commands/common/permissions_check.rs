@@ -1,6 +1,6 @@
 use crate::dbmodels::guild::Guild;
+use crate::mongo_conn::Db;
 use mongodb::bson::doc;
-use mongodb::Client;
 use serenity::{
     client::Context,
     model::{
@@ -13,19 +13,17 @@ use tracing::{debug, error};
 pub async fn check_if_mod(
     ctx: &Context,
     command: &ApplicationCommandInteraction,
-    mongo_client: &Client,
+    db: &Db,
 ) -> Result<bool, &'static str> {
     // Check if the user is an admin, admins always have permission.
     match &command.member {
         None => {}
         Some(mem) => match mem.permissions {
-            None => {}
-            Some(perms) => {
-                if perms.administrator() {
-                    debug!("User had admin perms - Allowing");
-                    return Ok(true);
-                }
+            Some(perms) if perms.administrator() => {
+                debug!("User had admin perms - Allowing");
+                return Ok(true);
             }
+            _ => {}
         },
     }
 
@@ -36,9 +34,8 @@ pub async fn check_if_mod(
     };
 
     // Try to get the guild from the database, returns an option if the guild was found.
-    let guild_doc_opt = match mongo_client
-        .database("botdb")
-        .collection("guilds")
+    let guild_doc_opt = match db
+        .guilds()
         .find_one(doc! {"guild_ID": guild_id.to_string()}, None)
         .await
     {
@@ -81,19 +78,17 @@ pub async fn check_if_mod(
 pub async fn check_if_mod_comp(
     ctx: &Context,
     command: &MessageComponentInteraction,
-    mongo_client: &Client,
+    db: &Db,
 ) -> Result<bool, &'static str> {
     // Check if the user is an admin, admins always have permission.
     match &command.member {
         None => {}
         Some(mem) => match mem.permissions {
-            None => {}
-            Some(perms) => {
-                if perms.administrator() {
-                    debug!("User had admin perms - Allowing");
-                    return Ok(true);
-                }
+            Some(perms) if perms.administrator() => {
+                debug!("User had admin perms - Allowing");
+                return Ok(true);
             }
+            _ => {}
         },
     }
 
@@ -104,9 +99,8 @@ pub async fn check_if_mod_comp(
     };
 
     // Try to get the guild from the database, returns an option if the guild was found.
-    let guild_doc_opt = match mongo_client
-        .database("botdb")
-        .collection("guilds")
+    let guild_doc_opt = match db
+        .guilds()
         .find_one(doc! {"guild_ID": guild_id.to_string()}, None)
         .await
     {
@@ -144,3 +138,28 @@ pub async fn check_if_mod_comp(
     };
     Ok(allowed)
 }
+
+/// Checks if the user owns the bot's application, or is on the team that does.
+pub async fn check_if_owner(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+) -> Result<bool, &'static str> {
+    let app_info = match ctx.http.get_current_application_info().await {
+        Ok(app_info) => app_info,
+        Err(err) => {
+            error!("{:?}", err);
+            return Err("Could not retrieve the application info.");
+        }
+    };
+
+    debug!("Owner check for {:?}", command.user.id);
+
+    if app_info.owner.id == command.user.id {
+        return Ok(true);
+    }
+    Ok(app_info.team.is_some_and(|team| {
+        team.members
+            .iter()
+            .any(|member| member.user.id == command.user.id)
+    }))
+}
@@ -0,0 +1,22 @@
+use serenity::builder::CreateComponents;
+use serenity::model::application::component::ButtonStyle;
+
+/// The buttons shown under the player messages, `/nowplaying` and the track announcements.
+pub fn player_buttons() -> CreateComponents {
+    let mut components = CreateComponents::default();
+    components.create_action_row(|row| {
+        row.create_button(|button| {
+            button
+                .custom_id("grab")
+                .label("Save")
+                .style(ButtonStyle::Secondary)
+        });
+        row.create_button(|button| {
+            button
+                .custom_id("like")
+                .emoji('❤')
+                .style(ButtonStyle::Secondary)
+        })
+    });
+    components
+}
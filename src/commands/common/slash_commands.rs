@@ -1,5 +1,5 @@
 use serenity::model::prelude::interaction::application_command::CommandDataOptionValue;
-use serenity::model::prelude::{PartialChannel, Role};
+use serenity::model::prelude::{Attachment, PartialChannel, Role};
 use serenity::model::{
     application::interaction::application_command::CommandDataOption, user::User,
 };
@@ -71,3 +71,11 @@ pub fn get_int(option_value: CommandDataOptionValue) -> Option<i64> {
     };
     value
 }
+#[allow(dead_code)]
+pub fn get_attachment(option_value: CommandDataOptionValue) -> Option<Attachment> {
+    let value: Option<Attachment> = match option_value {
+        CommandDataOptionValue::Attachment(attachment) => Some(attachment),
+        _ => None,
+    };
+    value
+}
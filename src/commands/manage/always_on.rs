@@ -0,0 +1,163 @@
+use mongodb::bson::doc;
+use mongodb::Collection;
+use serenity::model::application::command::Command;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::MessageFlags;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use tracing::debug;
+use tracing::{error, info, instrument, warn};
+
+use crate::commands::common::interaction_error::{channel_message_error, interaction_error_edit};
+use crate::commands::common::permissions_check::check_if_mod;
+use crate::dbmodels::guild::{default_channel_id, Guild as GuildStruct};
+use crate::mongo_conn::Db;
+use crate::player::join;
+
+#[instrument(skip(ctx, db))]
+pub async fn command(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    db: &Db,
+) {
+    // Joining can take longer than the three seconds Discord waits for a response.
+    let res = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|message| message.flags(MessageFlags::EPHEMERAL))
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+        return;
+    }
+
+    // Check if mod already.
+    match check_if_mod(ctx, command, db).await {
+        Ok(is_mod) => {
+            if !is_mod {
+                interaction_error_edit("You must be a mod to use this command.", command, ctx).await;
+                return;
+            }
+        }
+        Err(err) => {
+            warn!("{}", err);
+            interaction_error_edit(err, command, ctx).await;
+            return;
+        }
+    }
+
+    let guild = match command.guild_id.and_then(|id| id.to_guild_cached(&ctx.cache)) {
+        None => {
+            interaction_error_edit("This command must be run in a guild.", command, ctx).await;
+            return;
+        }
+        Some(guild) => guild,
+    };
+    let guild_id_str = guild.id.0.to_string();
+
+    let enable = match command.data.options.first().map(|opt| opt.name.as_str()) {
+        Some("on") => true,
+        Some("off") => false,
+        _ => {
+            interaction_error_edit("No subcommand was given.", command, ctx).await;
+            return;
+        }
+    };
+
+    let update = if enable {
+        // Stay in the channel the bot is already in, otherwise the one the mod is in.
+        let bot_channel = guild
+            .voice_states
+            .get(&ctx.cache.current_user_id())
+            .and_then(|state| state.channel_id);
+        let user_channel = guild
+            .voice_states
+            .get(&command.user.id)
+            .and_then(|state| state.channel_id);
+        let channel_id = match bot_channel.or(user_channel) {
+            Some(channel_id) => channel_id,
+            None => {
+                interaction_error_edit("Join a voice channel first.", command, ctx).await;
+                return;
+            }
+        };
+
+        if bot_channel.is_none() {
+            let (_, join_res) = join(ctx, guild.id, channel_id, db).await;
+            if let Err(err) = join_res {
+                error!("{:?}", err);
+                interaction_error_edit("Failed to join the voice channel.", command, ctx).await;
+                return;
+            }
+        }
+
+        doc! {"$set": {"always_on": true, "always_on_channel_ID": channel_id.0.to_string()}}
+    } else {
+        doc! {"$set": {
+            "always_on": false,
+            "always_on_channel_ID": default_channel_id(),
+            "saved_queue": [],
+        }}
+    };
+
+    let collection: Collection<GuildStruct> = db.guilds();
+    let update_res = match collection
+        .update_one(doc! {"guild_ID": guild_id_str}, update, None)
+        .await
+    {
+        Ok(res) => res,
+        Err(err) => {
+            error!("{:?}", err);
+            interaction_error_edit("Could not update the database.", command, ctx).await;
+            return;
+        }
+    };
+    debug!("{:?}", update_res);
+    debug!("Creating response...");
+    let res = command
+        .edit_original_interaction_response(&ctx.http, |message| {
+            if enable {
+                message.content("24/7 mode is on, the bot will stay in voice.")
+            } else {
+                message.content("24/7 mode is off.")
+            }
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+        channel_message_error("Could not send interaction message.", command, ctx).await;
+    } else {
+        info!("Response created.");
+    }
+}
+
+#[instrument(skip(ctx))]
+pub async fn register(ctx: &Context) {
+    let result = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("247")
+            .description("Keep the bot in voice around the clock. Mod only command.")
+            .create_option(|opt| {
+                opt.name("on")
+                    .description("Stay in the current voice channel and rejoin after restarts.")
+                    .kind(CommandOptionType::SubCommand)
+            })
+            .create_option(|opt| {
+                opt.name("off")
+                    .description("Leave again when idle or alone.")
+                    .kind(CommandOptionType::SubCommand)
+            })
+    })
+    .await;
+
+    match result {
+        Ok(command) => {
+            info!("Command {:?} registered successfully.", command);
+        }
+        Err(error) => {
+            error!("Could not create guild command! {:?}", error);
+        }
+    };
+}
@@ -0,0 +1,183 @@
+use mongodb::bson::doc;
+use mongodb::Collection;
+use serenity::model::application::command::Command;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::MessageFlags;
+use serenity::model::channel::ChannelType;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use tracing::debug;
+use tracing::{error, info, instrument, warn};
+
+use crate::commands::common::interaction_error::{channel_message_error, interaction_error};
+use crate::commands::common::permissions_check::check_if_mod;
+use crate::commands::common::slash_commands::{extract_vec, get_bool, get_channel, get_string};
+use crate::dbmodels::guild::{default_channel_id, AnnounceMode, Guild as GuildStruct};
+use crate::mongo_conn::Db;
+
+#[instrument(skip(ctx, db))]
+pub async fn command(ctx: &Context, command: &ApplicationCommandInteraction, db: &Db) {
+    // Check if mod already.
+    match check_if_mod(ctx, command, db).await {
+        Ok(is_mod) => {
+            if !is_mod {
+                interaction_error("You must be a mod to use this command.", command, ctx).await;
+                return;
+            }
+        }
+        Err(err) => {
+            warn!("{}", err);
+            interaction_error(err, command, ctx).await;
+            return;
+        }
+    }
+
+    let options = command.data.options.clone();
+    let mut mode_opt: Option<AnnounceMode> = None;
+    let mut channel_opt = None;
+    let mut tidy = false;
+    for tup in extract_vec(&options).await {
+        match tup.0 {
+            "mode" => match get_string(tup.1).as_deref() {
+                Some("off") => mode_opt = Some(AnnounceMode::Off),
+                Some("embed") => mode_opt = Some(AnnounceMode::Embed),
+                Some("compact") => mode_opt = Some(AnnounceMode::Compact),
+                _ => {
+                    interaction_error("'mode' param was invalid.", command, ctx).await;
+                    return;
+                }
+            },
+            "channel" => {
+                if let Some(x) = get_channel(tup.1) {
+                    channel_opt = Some(x)
+                } else {
+                    interaction_error("'channel' param was invalid.", command, ctx).await;
+                    return;
+                }
+            }
+            "tidy" => tidy = get_bool(tup.1).unwrap_or(false),
+            _ => {}
+        }
+    }
+
+    // Check to make sure its there!
+    let mode = match mode_opt {
+        Some(mode) => mode,
+        None => {
+            interaction_error("No mode provided.", command, ctx).await;
+            return;
+        }
+    };
+    let channel_id_str = match (mode, channel_opt) {
+        (AnnounceMode::Off, _) => default_channel_id(),
+        (_, Some(channel)) => channel.id.0.to_string(),
+        (_, None) => {
+            interaction_error("Pick a channel to announce in.", command, ctx).await;
+            return;
+        }
+    };
+
+    let guild_id_str = match command.guild_id {
+        None => {
+            interaction_error("This command must be run in a guild.", command, ctx).await;
+            return;
+        }
+        Some(id) => id.0.to_string(),
+    };
+
+    let mode_bson = match mongodb::bson::to_bson(&mode) {
+        Ok(bson) => bson,
+        Err(err) => {
+            error!("{}", err);
+            interaction_error("Could not convert the mode to bson.", command, ctx).await;
+            return;
+        }
+    };
+
+    let collection: Collection<GuildStruct> = db.guilds();
+    let update_res = match collection
+        .update_one(
+            doc! {"guild_ID": guild_id_str},
+            doc! {"$set": {
+                "announce_channel_ID": &channel_id_str,
+                "announce_mode": mode_bson,
+                "announce_tidy": tidy,
+            }},
+            None,
+        )
+        .await
+    {
+        Ok(res) => res,
+        Err(err) => {
+            error!("{:?}", err);
+            interaction_error("Could not update the database.", command, ctx).await;
+            return;
+        }
+    };
+    debug!("{:?}", update_res);
+    debug!("Creating response...");
+    let res = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message.flags(MessageFlags::EPHEMERAL);
+                    if mode == AnnounceMode::Off {
+                        message.content("Track starts will no longer be announced.")
+                    } else {
+                        message.content(format!(
+                            "Track starts will now be announced in <#{}>.",
+                            channel_id_str
+                        ))
+                    }
+                })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+        channel_message_error("Could not send interaction message.", command, ctx).await;
+    } else {
+        info!("Response created.");
+    }
+}
+
+#[instrument(skip(ctx))]
+pub async fn register(ctx: &Context) {
+    let result = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("announce")
+            .description("Announce every track that starts playing in a channel. Mod only command.")
+            .create_option(|opt| {
+                opt.name("mode")
+                    .description("How to announce tracks.")
+                    .kind(CommandOptionType::String)
+                    .add_string_choice("off", "off")
+                    .add_string_choice("embed", "embed")
+                    .add_string_choice("compact", "compact")
+                    .required(true)
+            })
+            .create_option(|opt| {
+                opt.name("channel")
+                    .description("The channel to announce in.")
+                    .kind(CommandOptionType::Channel)
+                    .channel_types(&[ChannelType::Text])
+                    .required(false)
+            })
+            .create_option(|opt| {
+                opt.name("tidy")
+                    .description("Delete the previous announcement when a new track starts.")
+                    .kind(CommandOptionType::Boolean)
+                    .required(false)
+            })
+    })
+    .await;
+
+    match result {
+        Ok(command) => {
+            info!("Command {:?} registered successfully.", command);
+        }
+        Err(error) => {
+            error!("Could not create guild command! {:?}", error);
+        }
+    };
+}
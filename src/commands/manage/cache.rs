@@ -0,0 +1,192 @@
+use mongodb::bson::doc;
+use mongodb::Collection;
+use serenity::model::application::command::Command;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::MessageFlags;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use tracing::debug;
+use tracing::{error, info, instrument, warn};
+
+use crate::commands::common::interaction_error::{channel_message_error, interaction_error};
+use crate::commands::common::permissions_check::{check_if_mod, check_if_owner};
+use crate::commands::common::slash_commands::{extract_vec, get_bool};
+use crate::dbmodels::guild::Guild as GuildStruct;
+use crate::mongo_conn::Db;
+use crate::player::cache::audio_cache;
+
+#[instrument(skip(ctx, db))]
+pub async fn command(ctx: &Context, command: &ApplicationCommandInteraction, db: &Db) {
+    let subcommand = match command.data.options.first() {
+        Some(subcommand) => subcommand,
+        None => {
+            interaction_error("No subcommand was given.", command, ctx).await;
+            return;
+        }
+    };
+
+    let content = match subcommand.name.as_str() {
+        "stats" => {
+            match check_if_owner(ctx, command).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    interaction_error("Only the bot owner can use this command.", command, ctx)
+                        .await;
+                    return;
+                }
+                Err(err) => {
+                    warn!("{}", err);
+                    interaction_error(err, command, ctx).await;
+                    return;
+                }
+            }
+
+            let cache = match audio_cache() {
+                Some(cache) => cache,
+                None => {
+                    interaction_error("The audio cache is not configured.", command, ctx).await;
+                    return;
+                }
+            };
+            let stats = cache.stats().await;
+            let lookups = stats.hits + stats.misses;
+            let hit_rate = if lookups == 0 {
+                0.0
+            } else {
+                stats.hits as f64 / lookups as f64 * 100.0
+            };
+            format!(
+                "**Hit rate:** {:.1}% ({} of {} plays since startup)\n**Disk usage:** {:.1} of {:.0} MB in {} files",
+                hit_rate,
+                stats.hits,
+                lookups,
+                stats.bytes as f64 / 1024.0 / 1024.0,
+                stats.max_bytes as f64 / 1024.0 / 1024.0,
+                stats.files
+            )
+        }
+        "enable" => {
+            // Check if mod already.
+            match check_if_mod(ctx, command, db).await {
+                Ok(is_mod) => {
+                    if !is_mod {
+                        interaction_error("You must be a mod to use this command.", command, ctx)
+                            .await;
+                        return;
+                    }
+                }
+                Err(err) => {
+                    warn!("{}", err);
+                    interaction_error(err, command, ctx).await;
+                    return;
+                }
+            }
+
+            let mut enabled_opt: Option<bool> = None;
+            for tup in extract_vec(&subcommand.options).await {
+                if tup.0 == "enabled" {
+                    enabled_opt = get_bool(tup.1);
+                }
+            }
+            let enabled = match enabled_opt {
+                Some(enabled) => enabled,
+                None => {
+                    interaction_error("'enabled' param was invalid.", command, ctx).await;
+                    return;
+                }
+            };
+
+            let guild_id_str = match command.guild_id {
+                None => {
+                    interaction_error("This command must be run in a guild.", command, ctx).await;
+                    return;
+                }
+                Some(id) => id.0.to_string(),
+            };
+
+            let collection: Collection<GuildStruct> = db.guilds();
+            match collection
+                .update_one(
+                    doc! {"guild_ID": guild_id_str},
+                    doc! {"$set": {"audio_cache": enabled}},
+                    None,
+                )
+                .await
+            {
+                Ok(res) => debug!("{:?}", res),
+                Err(err) => {
+                    error!("{:?}", err);
+                    interaction_error("Could not update the database.", command, ctx).await;
+                    return;
+                }
+            };
+
+            match (enabled, audio_cache().is_some()) {
+                (false, _) => "Tracks will no longer use the audio cache.".to_string(),
+                (true, true) => "Played tracks will be cached and replayed from disk.".to_string(),
+                (true, false) => {
+                    "Enabled, but the audio cache is not configured on this bot.".to_string()
+                }
+            }
+        }
+        _ => {
+            interaction_error("Unknown subcommand.", command, ctx).await;
+            return;
+        }
+    };
+
+    debug!("Creating response...");
+    let res = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message.flags(MessageFlags::EPHEMERAL);
+                    message.content(content)
+                })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+        channel_message_error("Could not send interaction message.", command, ctx).await;
+    } else {
+        info!("Response created.");
+    }
+}
+
+#[instrument(skip(ctx))]
+pub async fn register(ctx: &Context) {
+    let result = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("cache")
+            .description("The on-disk audio cache.")
+            .create_option(|opt| {
+                opt.name("stats")
+                    .description("Shows the hit rate and disk usage. Bot owner only command.")
+                    .kind(CommandOptionType::SubCommand)
+            })
+            .create_option(|opt| {
+                opt.name("enable")
+                    .description(
+                        "Plays and caches this server's tracks from disk. Mod only command.",
+                    )
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|sub| {
+                        sub.name("enabled")
+                            .description("Whether to use the cache.")
+                            .kind(CommandOptionType::Boolean)
+                            .required(true)
+                    })
+            })
+    })
+    .await;
+
+    match result {
+        Ok(command) => {
+            info!("Command {:?} registered successfully.", command);
+        }
+        Err(error) => {
+            error!("Could not create guild command! {:?}", error);
+        }
+    };
+}
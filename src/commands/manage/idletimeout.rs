@@ -0,0 +1,132 @@
+use mongodb::bson::doc;
+use mongodb::Collection;
+use serenity::model::application::command::Command;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::MessageFlags;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use tracing::debug;
+use tracing::{error, info, instrument, warn};
+
+use crate::commands::common::interaction_error::{channel_message_error, interaction_error};
+use crate::commands::common::permissions_check::check_if_mod;
+use crate::commands::common::slash_commands::{extract_vec, get_int};
+use crate::dbmodels::guild::Guild as GuildStruct;
+use crate::mongo_conn::Db;
+
+#[instrument(skip(ctx, db))]
+pub async fn command(ctx: &Context, command: &ApplicationCommandInteraction, db: &Db) {
+    // Check if mod already.
+    match check_if_mod(ctx, command, db).await {
+        Ok(is_mod) => {
+            if !is_mod {
+                interaction_error("You must be a mod to use this command.", command, ctx).await;
+                return;
+            }
+        }
+        Err(err) => {
+            warn!("{}", err);
+            interaction_error(err, command, ctx).await;
+            return;
+        }
+    }
+
+    let options = command.data.options.clone();
+    let mut minutes_opt: Option<i64> = None;
+    for tup in extract_vec(&options).await {
+        if tup.0 == "minutes" {
+            if let Some(x) = get_int(tup.1) {
+                minutes_opt = Some(x)
+            } else {
+                interaction_error("'minutes' param was invalid.", command, ctx).await;
+                return;
+            }
+        }
+    }
+
+    // Check to make sure its there!
+    let minutes = match minutes_opt {
+        Some(minutes) if minutes >= 0 => minutes,
+        _ => {
+            interaction_error("No timeout provided.", command, ctx).await;
+            return;
+        }
+    };
+
+    let guild_id_str = match command.guild_id {
+        None => {
+            interaction_error("This command must be run in a guild.", command, ctx).await;
+            return;
+        }
+        Some(id) => id.0.to_string(),
+    };
+
+    let collection: Collection<GuildStruct> = db.guilds();
+    let update_res = match collection
+        .update_one(
+            doc! {"guild_ID": guild_id_str},
+            doc! {"$set": {"idle_timeout": minutes}},
+            None,
+        )
+        .await
+    {
+        Ok(res) => res,
+        Err(err) => {
+            error!("{:?}", err);
+            interaction_error("Could not update the database.", command, ctx).await;
+            return;
+        }
+    };
+    debug!("{:?}", update_res);
+    debug!("Creating response...");
+    let res = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message.flags(MessageFlags::EPHEMERAL);
+                    if minutes == 0 {
+                        message.content("The bot will no longer leave when idle.")
+                    } else {
+                        message.content(format!(
+                            "The bot will now leave after {} minutes without playback.",
+                            minutes
+                        ))
+                    }
+                })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+        channel_message_error("Could not send interaction message.", command, ctx).await;
+    } else {
+        info!("Response created.");
+    }
+}
+
+#[instrument(skip(ctx))]
+pub async fn register(ctx: &Context) {
+    let result = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("idletimeout")
+            .description("Set how long the bot stays without playing anything. Mod only command.")
+            .create_option(|opt| {
+                opt.name("minutes")
+                    .description("Minutes without playback before leaving, 0 to never leave.")
+                    .kind(CommandOptionType::Integer)
+                    .min_int_value(0)
+                    .max_int_value(1440)
+                    .required(true)
+            })
+    })
+    .await;
+
+    match result {
+        Ok(command) => {
+            info!("Command {:?} registered successfully.", command);
+        }
+        Err(error) => {
+            error!("Could not create guild command! {:?}", error);
+        }
+    };
+}
@@ -1 +1,6 @@
+pub mod always_on;
+pub mod announce;
+pub mod cache;
+pub mod idletimeout;
 pub mod setmodrole;
+pub mod sponsorblock;
@@ -14,15 +14,12 @@ use crate::commands::common::interaction_error::{channel_message_error, interact
 use crate::commands::common::permissions_check::check_if_mod;
 use crate::commands::common::slash_commands::extract_vec;
 use crate::dbmodels::guild::Guild as GuildStruct;
+use crate::mongo_conn::Db;
 
-#[instrument(skip(ctx, mongo_client))]
-pub async fn command(
-    ctx: &Context,
-    command: &ApplicationCommandInteraction,
-    mongo_client: &mongodb::Client,
-) {
+#[instrument(skip(ctx, db))]
+pub async fn command(ctx: &Context, command: &ApplicationCommandInteraction, db: &Db) {
     // Check if mod already.
-    match check_if_mod(ctx, command, mongo_client).await {
+    match check_if_mod(ctx, command, db).await {
         Ok(is_mod) => {
             if !is_mod {
                 interaction_error("You must be a mod to use this command.", command, ctx).await;
@@ -75,7 +72,7 @@ pub async fn command(
         }
     };
 
-    let collection: Collection<GuildStruct> = mongo_client.database("botdb").collection("guilds");
+    let collection: Collection<GuildStruct> = db.guilds();
     let update_res = match collection
         .update_one(
             doc! {"guild_ID": guild_id_str},
@@ -0,0 +1,148 @@
+use mongodb::bson::doc;
+use mongodb::Collection;
+use serenity::model::application::command::Command;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::MessageFlags;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use tracing::debug;
+use tracing::{error, info, instrument, warn};
+
+use crate::commands::common::interaction_error::{channel_message_error, interaction_error};
+use crate::commands::common::permissions_check::check_if_mod;
+use crate::commands::common::slash_commands::{extract_vec, get_bool};
+use crate::config::bot_config;
+use crate::dbmodels::guild::Guild as GuildStruct;
+use crate::mongo_conn::Db;
+use crate::player::sponsorblock::CATEGORIES;
+
+#[instrument(skip(ctx, db))]
+pub async fn command(ctx: &Context, command: &ApplicationCommandInteraction, db: &Db) {
+    // Check if mod already.
+    match check_if_mod(ctx, command, db).await {
+        Ok(is_mod) => {
+            if !is_mod {
+                interaction_error("You must be a mod to use this command.", command, ctx).await;
+                return;
+            }
+        }
+        Err(err) => {
+            warn!("{}", err);
+            interaction_error(err, command, ctx).await;
+            return;
+        }
+    }
+
+    // Every category is skipped unless it is turned off.
+    let options = command.data.options.clone();
+    let mut enabled_opt: Option<bool> = None;
+    let mut categories: Vec<String> = CATEGORIES.iter().map(|c| c.to_string()).collect();
+    for tup in extract_vec(&options).await {
+        match tup.0 {
+            "enabled" => enabled_opt = get_bool(tup.1),
+            category if CATEGORIES.contains(&category) && get_bool(tup.1) == Some(false) => {
+                categories.retain(|c| c != category);
+            }
+            _ => {}
+        }
+    }
+
+    // Check to make sure its there!
+    let enabled = match enabled_opt {
+        Some(enabled) => enabled,
+        None => {
+            interaction_error("'enabled' param was invalid.", command, ctx).await;
+            return;
+        }
+    };
+    if !enabled {
+        categories.clear();
+    }
+
+    let guild_id_str = match command.guild_id {
+        None => {
+            interaction_error("This command must be run in a guild.", command, ctx).await;
+            return;
+        }
+        Some(id) => id.0.to_string(),
+    };
+
+    let collection: Collection<GuildStruct> = db.guilds();
+    let update_res = match collection
+        .update_one(
+            doc! {"guild_ID": guild_id_str},
+            doc! {"$set": {"sponsorblock_categories": &categories}},
+            None,
+        )
+        .await
+    {
+        Ok(res) => res,
+        Err(err) => {
+            error!("{:?}", err);
+            interaction_error("Could not update the database.", command, ctx).await;
+            return;
+        }
+    };
+    debug!("{:?}", update_res);
+    let feature_on = bot_config(ctx).await.features.sponsorblock;
+    debug!("Creating response...");
+    let res = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message.flags(MessageFlags::EPHEMERAL);
+                    if categories.is_empty() {
+                        message.content("Segments will no longer be skipped.")
+                    } else if !feature_on {
+                        message.content("Saved, but skipping segments is turned off on this bot.")
+                    } else {
+                        message.content(format!(
+                            "Skipping these segments on YouTube tracks: {}.",
+                            categories.join(", ")
+                        ))
+                    }
+                })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+        channel_message_error("Could not send interaction message.", command, ctx).await;
+    } else {
+        info!("Response created.");
+    }
+}
+
+#[instrument(skip(ctx))]
+pub async fn register(ctx: &Context) {
+    let result = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("sponsorblock")
+            .description("Skip sponsors and other segments of YouTube tracks. Mod only command.")
+            .create_option(|opt| {
+                opt.name("enabled")
+                    .description("Whether to skip segments at all.")
+                    .kind(CommandOptionType::Boolean)
+                    .required(true)
+            });
+        for category in CATEGORIES {
+            command.create_option(|opt| {
+                opt.name(category)
+                    .description(format!("Skip {} segments, on by default.", category))
+                    .kind(CommandOptionType::Boolean)
+                    .required(false)
+            });
+        }
+        command
+    })
+    .await;
+
+    match result {
+        Ok(command) => {
+            info!("Command {:?} registered successfully.", command);
+        }
+        Err(error) => {
+            error!("Could not create guild command! {:?}", error);
+        }
+    };
+}
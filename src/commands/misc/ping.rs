@@ -7,6 +7,8 @@ use serenity::model::prelude::Message;
 use serenity::prelude::Context;
 use tracing::{error, info};
 
+use crate::mongo_conn::Db;
+
 #[command]
 pub async fn ping_msg(ctx: &Context, msg: &Message) -> CommandResult {
     msg.channel_id.say(&ctx.http, "Pong!").await?;
@@ -14,11 +16,7 @@ pub async fn ping_msg(ctx: &Context, msg: &Message) -> CommandResult {
 }
 
 #[allow(unused)]
-pub async fn command(
-    ctx: &Context,
-    command: &ApplicationCommandInteraction,
-    mongo_client: &mongodb::Client,
-) {
+pub async fn command(ctx: &Context, command: &ApplicationCommandInteraction, db: &Db) {
     info!("Creating response...");
     let _res = command
         .create_interaction_response(&ctx.http, |response| {
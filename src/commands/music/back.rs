@@ -0,0 +1,148 @@
+use serenity::model::prelude::command::Command;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use tracing::{error, info};
+
+use crate::commands::common::interaction_error::interaction_error_edit;
+use crate::mongo_conn::{get_guild_doc, Db};
+use crate::player::enqueue;
+use crate::player::previous::{self, TrackReplayed};
+use crate::player::source::resolve;
+use crate::player::ytdl_error::YtdlError;
+
+/// Plays the track that played before the current one. The current track is paused and picks
+/// up where it left off once the previous track is over.
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
+    let _res = interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await;
+
+    let guild_id = match interaction.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            interaction_error_edit("This command must be run in a guild.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+    let call_lock = match manager.get(guild_id) {
+        Some(call_lock) => call_lock,
+        None => {
+            interaction_error_edit("I'm not in a voice channel.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    // Only looked at for now, the URL is taken off the stack once the track is queued.
+    let stacks = previous::stacks(ctx).await;
+    let url_opt = stacks
+        .lock()
+        .await
+        .get(&guild_id)
+        .and_then(|stack| stack.back().cloned());
+    let url = match url_opt {
+        Some(url) => url,
+        None => {
+            interaction_error_edit("Nothing was played before this.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let guild_doc = match get_guild_doc(db, guild_id.0.to_string(), interaction, ctx).await {
+        Some(guild_doc) => guild_doc,
+        None => return,
+    };
+
+    let source = match resolve(&url, guild_id, db).await {
+        Ok(source) => source,
+        Err(err) => {
+            error!("Error: {}", err);
+            let message = match YtdlError::from_input_error(&err) {
+                Some(YtdlError::Unknown) | None => "Failed to get the previous track.",
+                Some(reason) => reason.user_message(),
+            };
+            interaction_error_edit(message, interaction, ctx).await;
+            return;
+        }
+    };
+    let metadata = *source.input.metadata.clone();
+
+    let mut call = call_lock.lock().await;
+    if let Some(current) = call.queue().current() {
+        if let Err(err) = current.pause() {
+            error!("Failed to pause the current track. {}", err);
+        }
+    }
+    let track_handle = enqueue(
+        &mut call,
+        source,
+        guild_doc.volume,
+        interaction.user.id,
+        guild_id,
+        db,
+    )
+    .await;
+    track_handle
+        .typemap()
+        .write()
+        .await
+        .insert::<TrackReplayed>(true);
+    // Tracks that ended while this one resolved were pushed on top of it.
+    if let Some(stack) = stacks.lock().await.get_mut(&guild_id) {
+        if let Some(index) = stack.iter().rposition(|previous_url| *previous_url == url) {
+            stack.remove(index);
+        }
+    }
+
+    // Move it in front of the paused track and start it.
+    call.queue().modify_queue(|queue| {
+        if let Some(track) = queue.pop_back() {
+            queue.push_front(track);
+        }
+    });
+    if let Err(err) = track_handle.play() {
+        error!("Failed to play the previous track. {}", err);
+        interaction_error_edit("Failed to play the previous track.", interaction, ctx).await;
+        return;
+    }
+    drop(call);
+
+    info!("Creating response...");
+    let _res = interaction
+        .edit_original_interaction_response(&ctx.http, |message| {
+            message.embed(|embed| {
+                embed.title("Playing Previous Track");
+                embed.description(format!(
+                    "[{}]({})",
+                    metadata.title.unwrap_or("NONE".to_string()),
+                    metadata.source_url.unwrap_or("NONE".to_string())
+                ));
+                if let Some(thumbnail) = metadata.thumbnail {
+                    embed.image(thumbnail);
+                }
+                embed
+            })
+        })
+        .await;
+    info!("Response created.");
+}
+
+#[allow(dead_code)]
+pub async fn register(ctx: &Context) {
+    if let Err(err) = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("back")
+            .description("Plays the previous track again.")
+    })
+    .await
+    {
+        error!("Could not register back command! {}", err.to_string());
+        panic!()
+    }
+}
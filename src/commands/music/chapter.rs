@@ -0,0 +1,200 @@
+use serenity::model::prelude::command::{Command, CommandOptionType};
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use tracing::{error, info};
+
+use crate::commands::common::interaction_error::interaction_error_edit;
+use crate::commands::common::slash_commands::{extract_vec, get_int};
+use crate::mongo_conn::Db;
+use crate::player::source::{chapter_at, TrackChapters};
+use crate::player::{current_track, format_duration};
+
+/// Restarting a chapter instead of going back one is only done past this many seconds into it.
+const RESTART_THRESHOLD_SECS: u64 = 3;
+
+/// Lists and jumps between the chapters of the current track.
+#[allow(unused)]
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
+    let _res = interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await;
+
+    let subcommand = match interaction.data.options.first() {
+        Some(subcommand) => subcommand,
+        None => {
+            interaction_error_edit("No subcommand was given.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let track_handle = match current_track(ctx, interaction.guild_id).await {
+        Some(track_handle) => track_handle,
+        None => {
+            interaction_error_edit("There is nothing playing right now.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let chapters = track_handle
+        .typemap()
+        .read()
+        .await
+        .get::<TrackChapters>()
+        .cloned()
+        .unwrap_or_default();
+    if chapters.is_empty() {
+        interaction_error_edit("This track has no chapters.", interaction, ctx).await;
+        return;
+    }
+
+    let position = match track_handle.get_info().await {
+        Ok(info) => info.position,
+        Err(err) => {
+            error!("Failed to get the track info. {}", err);
+            interaction_error_edit("The track has already ended.", interaction, ctx).await;
+            return;
+        }
+    };
+    let current = chapter_at(&chapters, position);
+
+    let target = match subcommand.name.as_str() {
+        "list" => {
+            info!("Creating response...");
+            let _res = interaction
+                .edit_original_interaction_response(&ctx.http, |message| {
+                    message.embed(|embed| {
+                        embed.title("Chapters");
+                        if let Some(title) = &track_handle.metadata().title {
+                            embed.description(title);
+                        }
+                        // Embeds can hold at most 25 fields.
+                        for (index, chapter) in chapters.iter().enumerate().take(25) {
+                            let marker = if Some(index) == current { "▶ " } else { "" };
+                            embed.field(
+                                format!("{}{}. {}", marker, index + 1, chapter.title),
+                                format!(
+                                    "{} - {}",
+                                    format_duration(chapter.start),
+                                    format_duration(chapter.end)
+                                ),
+                                false,
+                            );
+                        }
+                        embed
+                    })
+                })
+                .await;
+            info!("Response created.");
+            return;
+        }
+        "next" => match current {
+            Some(index) if index + 1 < chapters.len() => index + 1,
+            Some(_) => {
+                interaction_error_edit("This is the last chapter.", interaction, ctx).await;
+                return;
+            }
+            None => 0,
+        },
+        "prev" => match current {
+            Some(index)
+                if position.saturating_sub(chapters[index].start).as_secs()
+                    >= RESTART_THRESHOLD_SECS =>
+            {
+                index
+            }
+            Some(index) => index.saturating_sub(1),
+            None => 0,
+        },
+        "goto" => {
+            let mut number = 0;
+            for tup in extract_vec(&subcommand.options).await {
+                if tup.0 == "number" {
+                    number = get_int(tup.1).unwrap_or(0);
+                }
+            }
+            if number < 1 || number as usize > chapters.len() {
+                interaction_error_edit(
+                    &format!("Pick a chapter between 1 and {}.", chapters.len()),
+                    interaction,
+                    ctx,
+                )
+                .await;
+                return;
+            }
+            number as usize - 1
+        }
+        _ => {
+            interaction_error_edit("Unknown subcommand.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let chapter = &chapters[target];
+    if let Err(err) = track_handle.seek_time(chapter.start) {
+        error!("Failed to seek to the chapter. {}", err);
+        interaction_error_edit("This track can't be seeked.", interaction, ctx).await;
+        return;
+    }
+
+    info!("Creating response...");
+    let _res = interaction
+        .edit_original_interaction_response(&ctx.http, |message| {
+            message.embed(|embed| {
+                embed.title("Jumped to Chapter");
+                embed.description(format!("{}. {}", target + 1, chapter.title));
+                embed.footer(|footer| {
+                    footer.text(format!(
+                        "Starts at {}, chapter {} of {}",
+                        format_duration(chapter.start),
+                        target + 1,
+                        chapters.len()
+                    ))
+                })
+            })
+        })
+        .await;
+    info!("Response created.");
+}
+
+#[allow(dead_code)]
+pub async fn register(ctx: &Context) {
+    if let Err(err) = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("chapter")
+            .description("Navigates the chapters of the current track.")
+            .create_option(|opt| {
+                opt.name("next")
+                    .description("Skips to the next chapter.")
+                    .kind(CommandOptionType::SubCommand)
+            })
+            .create_option(|opt| {
+                opt.name("prev")
+                    .description("Restarts the chapter, or goes back one if it just started.")
+                    .kind(CommandOptionType::SubCommand)
+            })
+            .create_option(|opt| {
+                opt.name("list")
+                    .description("Lists the chapters.")
+                    .kind(CommandOptionType::SubCommand)
+            })
+            .create_option(|opt| {
+                opt.name("goto")
+                    .description("Jumps to a chapter.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|sub| {
+                        sub.name("number")
+                            .description("The chapter number, as shown by /chapter list.")
+                            .kind(CommandOptionType::Integer)
+                            .min_int_value(1)
+                            .required(true)
+                    })
+            })
+    })
+    .await
+    {
+        error!("Could not register chapter command! {}", err.to_string());
+        panic!()
+    }
+}
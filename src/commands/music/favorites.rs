@@ -0,0 +1,146 @@
+use rand::seq::SliceRandom;
+use serenity::model::application::command::Command;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use tracing::{error, info};
+
+use crate::commands::common::interaction_error::interaction_error_edit;
+use crate::commands::common::slash_commands::{extract_vec, get_bool, get_int};
+use crate::commands::music::play::play_entries;
+use crate::mongo_conn::{liked_tracks, Db};
+use crate::player::format_duration;
+
+const PAGE_SIZE: usize = 10;
+
+#[allow(unused)]
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
+    interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await;
+
+    let subcommand = match interaction.data.options.first() {
+        Some(subcommand) => subcommand,
+        None => {
+            interaction_error_edit("No subcommand was given.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let mut page_opt: Option<i64> = None;
+    let mut shuffle = false;
+    for tup in extract_vec(&subcommand.options).await {
+        match tup.0 {
+            "page" => page_opt = get_int(tup.1),
+            "shuffle" => shuffle = get_bool(tup.1).unwrap_or(false),
+            _ => {}
+        }
+    }
+
+    let liked = match liked_tracks(db, interaction.user.id).await {
+        Ok(liked) => liked,
+        Err(err) => {
+            error!("{:?}", err);
+            interaction_error_edit("Could not read your liked tracks.", interaction, ctx).await;
+            return;
+        }
+    };
+    if liked.is_empty() {
+        interaction_error_edit(
+            "You haven't liked any tracks yet, use /like or the heart button.",
+            interaction,
+            ctx,
+        )
+        .await;
+        return;
+    }
+
+    match subcommand.name.as_str() {
+        "list" => {
+            let page_count = liked.len().div_ceil(PAGE_SIZE);
+            let page = (page_opt.unwrap_or(1).max(1) as usize).min(page_count);
+
+            info!("Creating response...");
+            let _res = interaction
+                .edit_original_interaction_response(&ctx.http, |message| {
+                    message.embed(|embed| {
+                        embed.title("Your Liked Tracks");
+                        let start = (page - 1) * PAGE_SIZE;
+                        for (index, track) in liked.iter().enumerate().skip(start).take(PAGE_SIZE) {
+                            let duration = track
+                                .duration_secs
+                                .map(|secs| format_duration(std::time::Duration::from_secs(secs)))
+                                .unwrap_or_else(|| "live".to_string());
+                            embed.field(
+                                format!("{}. {}", index + 1, track.title),
+                                format!("[{}]({})", duration, track.url),
+                                false,
+                            );
+                        }
+                        embed.footer(|footer| {
+                            footer.text(format!(
+                                "Page {} of {}, {} tracks",
+                                page,
+                                page_count,
+                                liked.len()
+                            ))
+                        })
+                    })
+                })
+                .await;
+            info!("Response created.");
+        }
+        "play" => {
+            let mut entries: Vec<String> = liked.into_iter().map(|track| track.url).collect();
+            if shuffle {
+                entries.shuffle(&mut rand::thread_rng());
+            } else {
+                // Oldest likes first, the way they were added.
+                entries.reverse();
+            }
+            play_entries(ctx, interaction, db, entries).await;
+        }
+        _ => {
+            interaction_error_edit("Unknown subcommand.", interaction, ctx).await;
+        }
+    }
+}
+
+#[allow(dead_code)]
+pub async fn register(ctx: &Context) {
+    if let Err(err) = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("favorites")
+            .description("Your liked tracks.")
+            .create_option(|opt| {
+                opt.name("list")
+                    .description("Lists your liked tracks.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|sub| {
+                        sub.name("page")
+                            .description("The page to show.")
+                            .kind(CommandOptionType::Integer)
+                            .min_int_value(1)
+                            .required(false)
+                    })
+            })
+            .create_option(|opt| {
+                opt.name("play")
+                    .description("Queues all of your liked tracks.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|sub| {
+                        sub.name("shuffle")
+                            .description("Play them in a random order.")
+                            .kind(CommandOptionType::Boolean)
+                            .required(false)
+                    })
+            })
+    })
+    .await
+    {
+        error!("Could not register favorites command! {}", err.to_string());
+        panic!()
+    }
+}
@@ -0,0 +1,173 @@
+use serenity::builder::CreateEmbed;
+use serenity::model::application::command::{Command, CommandOptionType};
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::model::prelude::User;
+use serenity::model::Timestamp;
+use serenity::prelude::Context;
+use serenity::utils::Colour;
+use songbird::tracks::TrackHandle;
+use tracing::{error, info};
+
+use crate::commands::common::interaction_error::{interaction_error, interaction_error_comp};
+use crate::commands::common::slash_commands::{extract_vec, get_bool};
+use crate::mongo_conn::{like_track, Db};
+use crate::player::{current_track, format_duration};
+
+/// DMs the user the track that is playing right now, optionally liking it as well.
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
+    let mut like = false;
+    for tup in extract_vec(&interaction.data.options).await {
+        if tup.0 == "like" {
+            like = get_bool(tup.1).unwrap_or(false);
+        }
+    }
+
+    let track_handle = match current_track(ctx, interaction.guild_id).await {
+        Some(track_handle) => track_handle,
+        None => {
+            interaction_error("There is nothing playing right now.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let embed = grab_embed(&track_handle).await;
+    let liked = like && like_or_log(db, &interaction.user, &track_handle).await;
+    let dm_sent = send_dm(ctx, &interaction.user, embed.clone()).await;
+
+    info!("Creating response...");
+    let res = interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message.ephemeral(true);
+                    if dm_sent {
+                        message.content(reply_text(liked))
+                    } else {
+                        message.content("Your DMs are closed, so here it is.");
+                        message.add_embed(embed)
+                    }
+                })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+    }
+    info!("Response created.");
+}
+
+/// The "Save" button on the player messages.
+pub async fn component(ctx: &Context, m_component: &MessageComponentInteraction, _db: &Db) {
+    let track_handle = match current_track(ctx, m_component.guild_id).await {
+        Some(track_handle) => track_handle,
+        None => {
+            interaction_error_comp("There is nothing playing right now.", m_component, ctx).await;
+            return;
+        }
+    };
+
+    let embed = grab_embed(&track_handle).await;
+    let dm_sent = send_dm(ctx, &m_component.user, embed.clone()).await;
+
+    let res = m_component
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message.ephemeral(true);
+                    if dm_sent {
+                        message.content(reply_text(false))
+                    } else {
+                        message.content("Your DMs are closed, so here it is.");
+                        message.add_embed(embed)
+                    }
+                })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+    }
+}
+
+/// The track's title, link and thumbnail, along with how far into it the grab happened.
+async fn grab_embed(track_handle: &TrackHandle) -> CreateEmbed {
+    let metadata = track_handle.metadata();
+    let position = track_handle
+        .get_info()
+        .await
+        .map(|state| state.position)
+        .unwrap_or_default();
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(metadata.title.clone().unwrap_or("NONE".to_string()))
+        .field("Grabbed at", format_duration(position), true)
+        .timestamp(Timestamp::now())
+        .color(Colour::from_rgb(0, 150, 255));
+    if let Some(source_url) = &metadata.source_url {
+        embed.url(source_url);
+    }
+    if let Some(duration) = metadata.duration {
+        embed.field("Duration", format_duration(duration), true);
+    }
+    if let Some(artist) = metadata.artist.clone().or_else(|| metadata.channel.clone()) {
+        embed.field("Artist", artist, true);
+    }
+    if let Some(thumbnail) = &metadata.thumbnail {
+        embed.thumbnail(thumbnail);
+    }
+    embed
+}
+
+/// Returns whether the DM went through, it doesn't when the user has DMs from servers turned off.
+async fn send_dm(ctx: &Context, user: &User, embed: CreateEmbed) -> bool {
+    match user
+        .direct_message(&ctx.http, |message| message.set_embed(embed))
+        .await
+    {
+        Ok(_) => true,
+        Err(err) => {
+            info!("Could not DM {}. {}", user.id, err);
+            false
+        }
+    }
+}
+
+async fn like_or_log(db: &Db, user: &User, track_handle: &TrackHandle) -> bool {
+    match like_track(db, user.id, track_handle.metadata()).await {
+        Ok(_) => true,
+        Err(err) => {
+            error!("Failed to like the track. {:?}", err);
+            false
+        }
+    }
+}
+
+fn reply_text(liked: bool) -> &'static str {
+    if liked {
+        "Sent it to your DMs and added it to your liked tracks."
+    } else {
+        "Sent it to your DMs."
+    }
+}
+
+#[allow(dead_code)]
+pub async fn register(ctx: &Context) {
+    if let Err(err) = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("grab")
+            .description("Sends you the current track in a DM.")
+            .create_option(|opt| {
+                opt.name("like")
+                    .description("Also add it to your liked tracks.")
+                    .kind(CommandOptionType::Boolean)
+                    .required(false)
+            })
+    })
+    .await
+    {
+        error!("Could not register grab command! {}", err.to_string());
+        panic!()
+    }
+}
@@ -0,0 +1,283 @@
+use mongodb::bson::doc;
+use mongodb::options::FindOptions;
+use mongodb::Collection;
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::futures::TryStreamExt;
+use serenity::model::application::command::Command;
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use tracing::{error, info};
+
+use crate::commands::common::interaction_error::{interaction_error_comp, interaction_error_edit};
+use crate::commands::common::slash_commands::{extract_vec, get_int};
+use crate::commands::music::play::play_query;
+use crate::dbmodels::history::HistoryEntry;
+use crate::mongo_conn::Db;
+
+const PAGE_SIZE: u64 = 10;
+
+#[allow(unused)]
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
+    interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await;
+
+    let guild_id_str = match interaction.guild_id {
+        Some(id) => id.0.to_string(),
+        None => {
+            interaction_error_edit("This command must be run in a guild.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let subcommand = match interaction.data.options.first() {
+        Some(subcommand) => subcommand,
+        None => {
+            interaction_error_edit("No subcommand was given.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let mut number_opt: Option<i64> = None;
+    for tup in extract_vec(&subcommand.options).await {
+        if tup.0 == "page" || tup.0 == "number" {
+            if let Some(x) = get_int(tup.1) {
+                number_opt = Some(x);
+            } else {
+                interaction_error_edit("'number' param was invalid.", interaction, ctx).await;
+                return;
+            }
+        }
+    }
+
+    match subcommand.name.as_str() {
+        "list" => {
+            let page = number_opt.unwrap_or(1).max(1) as u64;
+            let (embed, components) = match history_page(db, &guild_id_str, page).await {
+                Ok(page) => page,
+                Err(err) => {
+                    error!("{:?}", err);
+                    interaction_error_edit("Could not read the play history.", interaction, ctx)
+                        .await;
+                    return;
+                }
+            };
+
+            info!("Creating response...");
+            let _res = interaction
+                .edit_original_interaction_response(&ctx.http, |message| {
+                    message.set_embed(embed);
+                    message.set_components(components)
+                })
+                .await;
+            info!("Response created.");
+        }
+        "play" => {
+            let number = match number_opt {
+                Some(number) if number > 0 => number as u64,
+                _ => {
+                    interaction_error_edit("'number' param was missing.", interaction, ctx).await;
+                    return;
+                }
+            };
+
+            let collection: Collection<HistoryEntry> = db.history();
+            let options = FindOptions::builder()
+                .sort(doc! {"started_at": -1})
+                .skip(number - 1)
+                .limit(1)
+                .build();
+            let entry_res = match collection
+                .find(doc! {"guild_ID": guild_id_str}, options)
+                .await
+            {
+                Ok(mut cursor) => cursor.try_next().await,
+                Err(err) => Err(err),
+            };
+
+            let entry = match entry_res {
+                Ok(Some(entry)) => entry,
+                Ok(None) => {
+                    interaction_error_edit(
+                        "There is no history entry with that number.",
+                        interaction,
+                        ctx,
+                    )
+                    .await;
+                    return;
+                }
+                Err(err) => {
+                    error!("{:?}", err);
+                    interaction_error_edit("Could not read the play history.", interaction, ctx)
+                        .await;
+                    return;
+                }
+            };
+
+            play_query(ctx, interaction, db, entry.url).await;
+        }
+        _ => {
+            interaction_error_edit("Unknown subcommand.", interaction, ctx).await;
+        }
+    }
+}
+
+/// Handles the page buttons, the custom ID is `history:<page>`.
+pub async fn component(ctx: &Context, m_component: &MessageComponentInteraction, db: &Db) {
+    let guild_id_str = match m_component.guild_id {
+        Some(id) => id.0.to_string(),
+        None => return,
+    };
+    let page: u64 = match m_component
+        .data
+        .custom_id
+        .split(':')
+        .nth(1)
+        .and_then(|page| page.parse().ok())
+    {
+        Some(page) => page,
+        None => {
+            interaction_error_comp("Invalid page.", m_component, ctx).await;
+            return;
+        }
+    };
+
+    let (embed, components) = match history_page(db, &guild_id_str, page).await {
+        Ok(page) => page,
+        Err(err) => {
+            error!("{:?}", err);
+            interaction_error_comp("Could not read the play history.", m_component, ctx).await;
+            return;
+        }
+    };
+
+    let res = m_component
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|message| {
+                    message.set_embed(embed);
+                    message.set_components(components)
+                })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+    }
+}
+
+async fn history_page(
+    db: &Db,
+    guild_id_str: &str,
+    page: u64,
+) -> mongodb::error::Result<(CreateEmbed, CreateComponents)> {
+    let collection: Collection<HistoryEntry> = db.history();
+
+    let total = collection
+        .count_documents(doc! {"guild_ID": guild_id_str}, None)
+        .await?;
+    let page_count = total.div_ceil(PAGE_SIZE).max(1);
+    let page = page.clamp(1, page_count);
+
+    let options = FindOptions::builder()
+        .sort(doc! {"started_at": -1})
+        .skip((page - 1) * PAGE_SIZE)
+        .limit(PAGE_SIZE as i64)
+        .build();
+    let entries: Vec<HistoryEntry> = collection
+        .find(doc! {"guild_ID": guild_id_str}, options)
+        .await?
+        .try_collect()
+        .await?;
+
+    let mut description = String::new();
+    for (count, entry) in entries.iter().enumerate() {
+        let number = (page - 1) * PAGE_SIZE + count as u64 + 1;
+        description.push_str(&format!(
+            "`{}.` [{}]({}) - <@{}> <t:{}:R>{}\n",
+            number,
+            entry.title,
+            entry.url,
+            entry.requester_ID,
+            entry.started_at.timestamp_millis() / 1000,
+            if entry.skipped { " (skipped)" } else { "" }
+        ));
+    }
+    if description.is_empty() {
+        description = "Nothing has been played yet...".to_string();
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed.title("Play History");
+    embed.description(description);
+    embed.footer(|footer| {
+        footer.text(format!(
+            "Page {}/{} - Use /history play <number> to replay a track.",
+            page, page_count
+        ))
+    });
+
+    let mut components = CreateComponents::default();
+    components.create_action_row(|row| {
+        row.create_button(|button| {
+            button
+                .custom_id(format!("history:{}", page.saturating_sub(1)))
+                .label("Previous")
+                .style(ButtonStyle::Secondary)
+                .disabled(page <= 1)
+        });
+        row.create_button(|button| {
+            button
+                .custom_id(format!("history:{}", page + 1))
+                .label("Next")
+                .style(ButtonStyle::Secondary)
+                .disabled(page >= page_count)
+        })
+    });
+
+    Ok((embed, components))
+}
+
+#[allow(dead_code)]
+pub async fn register(ctx: &Context) {
+    if let Err(err) = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("history")
+            .description("The tracks that were played in this server.")
+            .create_option(|opt| {
+                opt.name("list")
+                    .description("Lists the recently played tracks.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|sub_opt| {
+                        sub_opt
+                            .name("page")
+                            .description("The page to show.")
+                            .kind(CommandOptionType::Integer)
+                            .min_int_value(1)
+                    })
+            })
+            .create_option(|opt| {
+                opt.name("play")
+                    .description("Queues a track from the history again.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|sub_opt| {
+                        sub_opt
+                            .name("number")
+                            .description("The number of the track in /history list.")
+                            .kind(CommandOptionType::Integer)
+                            .min_int_value(1)
+                            .required(true)
+                    })
+            })
+    })
+    .await
+    {
+        error!("Could not register history command! {}", err.to_string());
+        panic!()
+    }
+}
@@ -4,12 +4,11 @@ use serenity::model::prelude::interaction::{application_command::*, InteractionR
 use serenity::prelude::Context;
 use tracing::{error, info};
 
+use crate::mongo_conn::Db;
+use crate::player::join;
+
 #[allow(unused)]
-pub async fn command(
-    ctx: &Context,
-    interaction: &ApplicationCommandInteraction,
-    mongo_client: &mongodb::Client,
-) {
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
     let guild = interaction
         .guild_id
         .unwrap()
@@ -32,12 +31,7 @@ pub async fn command(
         .await;
     info!("Response created.");
 
-    let manager = songbird::get(ctx)
-        .await
-        .expect("Songbird Voice client placed in at initialisation.")
-        .clone();
-
-    manager.join(guild.id, vc).await;
+    join(ctx, guild.id, vc, db).await;
 }
 #[allow(dead_code)]
 pub async fn register(ctx: &Context) {
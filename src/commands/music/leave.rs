@@ -1,4 +1,6 @@
 use crate::commands::common::interaction_error::interaction_error;
+use crate::mongo_conn::Db;
+use crate::player::guild_state::guild_state;
 use serenity::model::application::interaction::MessageFlags;
 use serenity::model::prelude::command::*;
 use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
@@ -6,11 +8,7 @@ use serenity::prelude::*;
 use tracing::{error, info};
 
 #[allow(unused)]
-pub async fn command(
-    ctx: &Context,
-    interaction: &ApplicationCommandInteraction,
-    mongo_client: &mongodb::Client,
-) {
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
     let guild = interaction
         .guild_id
         .unwrap()
@@ -27,6 +25,7 @@ pub async fn command(
 
     let has_handler = manager.get(guild.id).is_some();
     if has_handler {
+        guild_state(ctx, guild.id).await.cancel_playlists().await;
         if let Err(e) = manager.remove(guild.id).await {
             interaction_error("Failed to leave, try again in a moment.", interaction, ctx).await;
         }
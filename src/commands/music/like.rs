@@ -0,0 +1,89 @@
+use serenity::model::application::command::Command;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use tracing::{error, info};
+
+use crate::commands::common::interaction_error::{interaction_error, interaction_error_comp};
+use crate::mongo_conn::{like_track, Db};
+use crate::player::current_track;
+
+/// Adds the current track to the user's liked tracks.
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
+    let track_handle = match current_track(ctx, interaction.guild_id).await {
+        Some(track_handle) => track_handle,
+        None => {
+            interaction_error("There is nothing playing right now.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let content = match like_track(db, interaction.user.id, track_handle.metadata()).await {
+        Ok(true) => "Added it to your liked tracks.",
+        Ok(false) => "You already liked this track.",
+        Err(err) => {
+            error!("{:?}", err);
+            interaction_error("Could not update the database.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    info!("Creating response...");
+    let res = interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| message.ephemeral(true).content(content))
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+    }
+    info!("Response created.");
+}
+
+/// The heart button on the player messages.
+pub async fn component(ctx: &Context, m_component: &MessageComponentInteraction, db: &Db) {
+    let track_handle = match current_track(ctx, m_component.guild_id).await {
+        Some(track_handle) => track_handle,
+        None => {
+            interaction_error_comp("There is nothing playing right now.", m_component, ctx).await;
+            return;
+        }
+    };
+
+    let content = match like_track(db, m_component.user.id, track_handle.metadata()).await {
+        Ok(true) => "Added it to your liked tracks.",
+        Ok(false) => "You already liked this track.",
+        Err(err) => {
+            error!("{:?}", err);
+            interaction_error_comp("Could not update the database.", m_component, ctx).await;
+            return;
+        }
+    };
+
+    let res = m_component
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| message.ephemeral(true).content(content))
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+    }
+}
+
+#[allow(dead_code)]
+pub async fn register(ctx: &Context) {
+    if let Err(err) = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("like")
+            .description("Adds the current track to your liked tracks.")
+    })
+    .await
+    {
+        error!("Could not register like command! {}", err.to_string());
+        panic!()
+    }
+}
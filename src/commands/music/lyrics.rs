@@ -0,0 +1,340 @@
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::http::Http;
+use serenity::model::application::command::Command;
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::channel::Message;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use songbird::tracks::{PlayMode, TrackHandle};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::commands::common::interaction_error::{interaction_error_comp, interaction_error_edit};
+use crate::commands::common::slash_commands::{extract_vec, get_bool, get_int};
+use crate::lyrics::{self, FoundLyrics, LyricLine, Lyrics, TrackLyrics};
+use crate::mongo_conn::Db;
+use crate::player::current_track;
+
+const PAGE_LINES: usize = 30;
+const PAGE_CHARS: usize = 1800;
+
+/// How often karaoke checks the position of the track.
+const KARAOKE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Lines shown around the current one in karaoke mode.
+const KARAOKE_CONTEXT: usize = 3;
+
+#[allow(unused)]
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
+    interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await;
+
+    let mut page: i64 = 1;
+    let mut karaoke = false;
+    for tup in extract_vec(&interaction.data.options).await {
+        match tup.0 {
+            "page" => page = get_int(tup.1).unwrap_or(1),
+            "karaoke" => karaoke = get_bool(tup.1).unwrap_or(false),
+            _ => {}
+        }
+    }
+
+    let track_handle = match current_track(ctx, interaction.guild_id).await {
+        Some(track_handle) => track_handle,
+        None => {
+            interaction_error_edit("There is nothing playing right now.", interaction, ctx).await;
+            return;
+        }
+    };
+    let found = match track_lyrics(&track_handle).await {
+        Some(found) => found,
+        None => {
+            interaction_error_edit("No lyrics were found for this track.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    if karaoke {
+        let lines = match &found.lyrics {
+            Lyrics::Synced(lines) => lines.clone(),
+            Lyrics::Plain(_) => {
+                interaction_error_edit(
+                    "Karaoke needs synced lyrics, these only have plain text.",
+                    interaction,
+                    ctx,
+                )
+                .await;
+                return;
+            }
+        };
+
+        info!("Creating response...");
+        let message = interaction
+            .edit_original_interaction_response(&ctx.http, |message| {
+                message.set_embed(karaoke_embed(&track_handle, &lines, None, found.source))
+            })
+            .await;
+        info!("Response created.");
+        match message {
+            Ok(message) => {
+                tokio::spawn(karaoke_loop(
+                    ctx.http.clone(),
+                    message,
+                    track_handle,
+                    lines,
+                    found.source,
+                ));
+            }
+            Err(err) => error!("{}", err),
+        }
+        return;
+    }
+
+    let (embed, components) = lyrics_page(&track_handle, &found, page.max(1) as usize);
+    info!("Creating response...");
+    let _res = interaction
+        .edit_original_interaction_response(&ctx.http, |message| {
+            message.set_embed(embed);
+            message.set_components(components)
+        })
+        .await;
+    info!("Response created.");
+}
+
+/// Handles the page buttons, the custom ID is `lyrics:<page>`.
+#[allow(unused)]
+pub async fn component(ctx: &Context, m_component: &MessageComponentInteraction, db: &Db) {
+    let page: usize = match m_component
+        .data
+        .custom_id
+        .split(':')
+        .nth(1)
+        .and_then(|page| page.parse().ok())
+    {
+        Some(page) => page,
+        None => {
+            interaction_error_comp("Invalid page.", m_component, ctx).await;
+            return;
+        }
+    };
+
+    let track_handle = match current_track(ctx, m_component.guild_id).await {
+        Some(track_handle) => track_handle,
+        None => {
+            interaction_error_comp("There is nothing playing right now.", m_component, ctx).await;
+            return;
+        }
+    };
+    let found = match track_lyrics(&track_handle).await {
+        Some(found) => found,
+        None => {
+            interaction_error_comp("No lyrics were found for this track.", m_component, ctx).await;
+            return;
+        }
+    };
+
+    let (embed, components) = lyrics_page(&track_handle, &found, page);
+    let res = m_component
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|message| {
+                    message.set_embed(embed);
+                    message.set_components(components)
+                })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+    }
+}
+
+/// The lyrics of the track, looked up once and kept in its typemap for the page buttons.
+async fn track_lyrics(track_handle: &TrackHandle) -> Option<FoundLyrics> {
+    if let Some(found) = track_handle.typemap().read().await.get::<TrackLyrics>() {
+        return found.clone();
+    }
+    let found = lyrics::find(track_handle.metadata()).await;
+    track_handle
+        .typemap()
+        .write()
+        .await
+        .insert::<TrackLyrics>(found.clone());
+    found
+}
+
+/// Splits the lyrics into pages that fit in an embed.
+fn pages(text: &str) -> Vec<String> {
+    let mut pages = vec![];
+    let mut page = String::new();
+    let mut line_count = 0;
+    for line in text.lines() {
+        if line_count >= PAGE_LINES || page.len() + line.len() + 1 > PAGE_CHARS {
+            pages.push(std::mem::take(&mut page));
+            line_count = 0;
+        }
+        page.push_str(line);
+        page.push('\n');
+        line_count += 1;
+    }
+    if !page.trim().is_empty() || pages.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+fn lyrics_page(
+    track_handle: &TrackHandle,
+    found: &FoundLyrics,
+    page: usize,
+) -> (CreateEmbed, CreateComponents) {
+    let pages = pages(&found.lyrics.text());
+    let page_count = pages.len();
+    let page = page.clamp(1, page_count);
+
+    let mut embed = CreateEmbed::default();
+    embed.title(track_handle.metadata().title.as_deref().unwrap_or("Lyrics"));
+    if let Some(source_url) = &track_handle.metadata().source_url {
+        embed.url(source_url);
+    }
+    embed.description(&pages[page - 1]);
+    embed.footer(|footer| {
+        footer.text(format!(
+            "Page {}/{} - Lyrics from {}.",
+            page, page_count, found.source
+        ))
+    });
+
+    let mut components = CreateComponents::default();
+    if page_count > 1 {
+        components.create_action_row(|row| {
+            row.create_button(|button| {
+                button
+                    .custom_id(format!("lyrics:{}", page.saturating_sub(1)))
+                    .label("Previous")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(page <= 1)
+            });
+            row.create_button(|button| {
+                button
+                    .custom_id(format!("lyrics:{}", page + 1))
+                    .label("Next")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(page >= page_count)
+            })
+        });
+    }
+
+    (embed, components)
+}
+
+/// The lines around the current one, with the current line highlighted.
+fn karaoke_embed(
+    track_handle: &TrackHandle,
+    lines: &[LyricLine],
+    current: Option<usize>,
+    source: &str,
+) -> CreateEmbed {
+    let start = current.map_or(0, |index| index.saturating_sub(KARAOKE_CONTEXT));
+    let end = (current.map_or(0, |index| index + 1) + KARAOKE_CONTEXT).min(lines.len());
+
+    let mut description = String::new();
+    for (index, line) in lines.iter().enumerate().take(end).skip(start) {
+        let text = if line.text.is_empty() {
+            "♪"
+        } else {
+            &line.text
+        };
+        if Some(index) == current {
+            description.push_str(&format!("▶ **{}**\n", text));
+        } else {
+            description.push_str(&format!("{}\n", text));
+        }
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed.title(format!(
+        "Karaoke - {}",
+        track_handle
+            .metadata()
+            .title
+            .as_deref()
+            .unwrap_or("Unknown")
+    ));
+    embed.description(description);
+    embed.footer(|footer| footer.text(format!("Lyrics from {}.", source)));
+    embed
+}
+
+/// Follows the track, moving the highlight whenever a new line starts. Stops once the track is
+/// over.
+async fn karaoke_loop(
+    http: Arc<Http>,
+    mut message: Message,
+    track_handle: TrackHandle,
+    lines: Vec<LyricLine>,
+    source: &'static str,
+) {
+    let mut shown: Option<usize> = None;
+    loop {
+        tokio::time::sleep(KARAOKE_INTERVAL).await;
+        let info = match track_handle.get_info().await {
+            Ok(info) => info,
+            Err(_) => break,
+        };
+        if matches!(info.playing, PlayMode::Stop | PlayMode::End) {
+            break;
+        }
+
+        let current = lines.iter().rposition(|line| line.time <= info.position);
+        if current == shown {
+            continue;
+        }
+        shown = current;
+        let embed = karaoke_embed(&track_handle, &lines, current, source);
+        if let Err(err) = message
+            .edit(&http, |message| message.set_embed(embed))
+            .await
+        {
+            error!("Failed to update the karaoke message. {}", err);
+            return;
+        }
+    }
+
+    let _res = message
+        .edit(&http, |message| message.content("The track is over."))
+        .await;
+}
+
+#[allow(dead_code)]
+pub async fn register(ctx: &Context) {
+    if let Err(err) = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("lyrics")
+            .description("Shows the lyrics of the current track.")
+            .create_option(|opt| {
+                opt.name("page")
+                    .description("The page to show.")
+                    .kind(CommandOptionType::Integer)
+                    .min_int_value(1)
+                    .required(false)
+            })
+            .create_option(|opt| {
+                opt.name("karaoke")
+                    .description("Follow along, highlighting the current line of synced lyrics.")
+                    .kind(CommandOptionType::Boolean)
+                    .required(false)
+            })
+    })
+    .await
+    {
+        error!("Could not register lyrics command! {}", err.to_string());
+        panic!()
+    }
+}
@@ -1,7 +1,16 @@
 pub mod play;
+pub mod back;
+pub mod chapter;
+pub mod favorites;
+pub mod grab;
+pub mod history;
 pub mod join;
 pub mod leave;
+pub mod like;
+pub mod lyrics;
 pub mod nowplaying;
 pub mod queue;
 pub mod skip;
+pub mod stats;
+pub mod unlike;
 pub mod volume;
@@ -3,12 +3,12 @@ use serenity::model::prelude::interaction::{application_command::*, InteractionR
 use serenity::prelude::Context;
 use tracing::{error, info};
 
+use crate::commands::common::player_buttons::player_buttons;
+use crate::mongo_conn::Db;
+use crate::player::source::{chapter_at, TrackChapters};
+
 #[allow(unused)]
-pub async fn command(
-    ctx: &Context,
-    interaction: &ApplicationCommandInteraction,
-    mongo_client: &mongodb::Client,
-) {
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
     interaction
         .create_interaction_response(&ctx.http, |response| {
             response.interaction_response_data(|message| message.ephemeral(true));
@@ -33,7 +33,7 @@ pub async fn command(
     if let Some(handler_lock) = manager.get(guild.id) {
         let mut handler = handler_lock.lock().await;
         let queue = handler.queue().current_queue();
-        let track_handle = match queue.get(0) {
+        let track_handle = match queue.first() {
             None => {
                 info!("Creating response...");
                 let _res = interaction
@@ -58,6 +58,17 @@ pub async fn command(
             Some(track_handle) => track_handle,
         };
 
+        let chapter = match track_handle.get_info().await {
+            Ok(info) => {
+                let typemap = track_handle.typemap().read().await;
+                typemap.get::<TrackChapters>().and_then(|chapters| {
+                    chapter_at(chapters, info.position)
+                        .map(|index| format!("{}. {}", index + 1, chapters[index].title))
+                })
+            }
+            Err(_) => None,
+        };
+
         info!("Creating response...");
         let _res = interaction
             .edit_original_interaction_response(&ctx.http, |message| {
@@ -68,6 +79,10 @@ pub async fn command(
                         embed.description(track_title);
                     }
 
+                    if let Some(chapter) = &chapter {
+                        embed.field("Chapter", chapter, false);
+                    }
+
                     if let Some(source_url) = &track_handle.metadata().source_url {
                         embed.url(source_url);
                     }
@@ -78,11 +93,11 @@ pub async fn command(
 
                     embed
                 });
+                message.set_components(player_buttons());
                 message
             })
             .await;
         info!("Response created.");
-    } else {
     }
 }
 
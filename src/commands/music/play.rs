@@ -1,22 +1,40 @@
+use serde_json::Value;
 use serenity::async_trait;
+use serenity::builder::CreateEmbed;
+use serenity::http::Http;
 use serenity::model::application::command::Command as interaction_command;
+use serenity::model::channel::Message;
 use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::autocomplete::AutocompleteInteraction;
 use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
-use serenity::model::prelude::GuildId;
+use serenity::model::prelude::{ChannelId, GuildId, UserId};
 use serenity::prelude::Context;
-use songbird::input::{ytdl_search, Metadata};
-use songbird::{create_player, ytdl, Event, EventContext, EventHandler, Songbird, TrackEvent};
+use songbird::input::Metadata;
+use songbird::{Call, Event, EventContext, EventHandler, Songbird, TrackEvent};
 use std::fmt::Display;
-use std::process::Stdio;
 use std::str::from_utf8;
-use std::sync::{Arc, Mutex};
-use tokio::process::Command as TokioCommand;
-use tracing::{error, info};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
 use url::Url;
 
 use crate::commands::common::interaction_error::interaction_error_edit;
 use crate::commands::common::slash_commands::extract_vec;
-use crate::mongo_conn::get_guild_doc;
+use crate::mongo_conn::{cached_metadata_by_prefix, get_guild_doc, liked_tracks, Db};
+use crate::player::announce::notice_channel;
+use crate::player::failures::{give_up, report, FailureReason, MAX_CONSECUTIVE_FAILURES};
+use crate::player::governor::governor;
+use crate::player::guild_state::guild_state;
+use crate::player::metadata_cache::{
+    cached_entry, normalize_query, playlist_key, search_key, store, PLAYLIST_TTL,
+};
+use crate::player::prefetch::PendingEntries;
+use crate::player::previous::TrackReplayed;
+use crate::player::source::resolve;
+use crate::player::ytdl::extractor;
+use crate::player::ytdl_error::YtdlError;
+use crate::player::{enqueue, join};
+
 enum QueryType {
     Url,
     Playlist,
@@ -24,11 +42,7 @@ enum QueryType {
 }
 
 #[allow(unused)]
-pub async fn command(
-    ctx: &Context,
-    interaction: &ApplicationCommandInteraction,
-    mongo_client: &mongodb::Client,
-) {
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
     interaction
         .create_interaction_response(&ctx.http, |response| {
             response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
@@ -55,6 +69,17 @@ pub async fn command(
         }
     };
 
+    play_query(ctx, interaction, db, query_string).await;
+}
+
+/// Resolves the query and queues it, the interaction must already be deferred.
+#[allow(unused)]
+pub async fn play_query(
+    ctx: &Context,
+    interaction: &ApplicationCommandInteraction,
+    db: &Db,
+    query_string: String,
+) {
     let query_type: QueryType = match Url::parse(&query_string) {
         Ok(url_obj) => {
             if let Some(playlist_param) = url_obj.query_pairs().find(|pair| pair.0 == "list") {
@@ -66,6 +91,59 @@ pub async fn command(
         Err(_) => QueryType::Search,
     };
 
+    let entries: Vec<String> = match query_type {
+        QueryType::Url | QueryType::Search => vec![query_string],
+        QueryType::Playlist => {
+            load_playlist(ctx, interaction, db, &query_string).await;
+            return;
+        }
+    };
+
+    play_entries(ctx, interaction, db, entries).await;
+}
+
+/// Resolves and queues the first entry right away, the rest are resolved one at a time as the
+/// tracks before them end. Each entry is a URL or a search query.
+#[allow(unused)]
+pub async fn play_entries(
+    ctx: &Context,
+    interaction: &ApplicationCommandInteraction,
+    db: &Db,
+    mut entries: Vec<String>,
+) {
+    if entries.is_empty() {
+        interaction_error_edit("There was nothing to play.", interaction, ctx).await;
+        return;
+    }
+
+    let pending_count = entries.len() - 1;
+    let pending = PendingEntries::new(entries, interaction.guild_id.unwrap(), db);
+    let (source_metadata, position) = match start_playback(ctx, interaction, db, pending).await {
+        Some(queued) => queued,
+        None => return,
+    };
+
+    // Send the response
+    info!("Creating response...");
+    let footer =
+        (pending_count > 0).then(|| format!("{} more tracks will be queued.", pending_count));
+    let _res = interaction
+        .edit_original_interaction_response(&ctx.http, |message| {
+            message.set_embed(queued_embed(source_metadata, position, footer))
+        })
+        .await;
+    info!("Response created.");
+}
+
+/// Joins the user's channel if needed and queues the first pending entry with the others behind
+/// it. Failures are reported on the interaction, which must already be deferred.
+#[allow(unused)]
+async fn start_playback(
+    ctx: &Context,
+    interaction: &ApplicationCommandInteraction,
+    db: &Db,
+    pending: Arc<PendingEntries>,
+) -> Option<(Metadata, usize)> {
     // Get the call
     let manager = songbird::get(ctx)
         .await
@@ -82,88 +160,180 @@ pub async fn command(
             let voice_state = guild.voice_states.get(&interaction.user.id).unwrap();
             let vc = voice_state.channel_id.unwrap();
             let vc_name = vc.name(&ctx.cache).await.unwrap();
-            manager.join(guild.id, vc).await.0
+            join(ctx, guild.id, vc, db).await.0
         }
     };
-    let mut playlist: Vec<Metadata> = vec![];
-    // Get the track
-    let input_res = match query_type {
-        QueryType::Url => ytdl(query_string).await,
-        QueryType::Search => ytdl_search(query_string).await,
-        QueryType::Playlist => {
-            playlist = match ytdl_playlist(&query_string).await {
-                Ok(playlist) => playlist,
-                Err(e) => {
-                    error!("{}", e);
-                    interaction_error_edit("Failed to get the playlist.", interaction, ctx);
-                    return;
-                }
-            };
-            playlist.reverse();
-            let meta = playlist.pop().unwrap();
-            let new_query: String;
-            let source_url: Url = match meta.source_url {
-                Some(url_str) => Url::parse(&url_str).unwrap(),
-                None => return,
-            };
 
-            ytdl(source_url.to_string()).await
-        }
-    };
+    let guild_id_str = interaction.guild_id.unwrap().0.to_string();
+
+    // Try to get the guild from the database, returns an option if the guild was found.
+    let guild_doc = get_guild_doc(db, guild_id_str, interaction, ctx).await?;
 
-    let source = match input_res {
-        Ok(input) => input,
+    match queue_entries(
+        ctx,
+        &call_lock,
+        pending,
+        guild_doc.volume,
+        interaction.user.id,
+        guild.id,
+        db,
+    )
+    .await
+    {
+        Ok(queued) => Some(queued),
         Err(err) => {
             error!("Error: {}", err);
-            interaction_error_edit("Failed to get the track.", interaction, ctx).await;
-            return;
+            let message = YtdlError::from_input_error(&err)
+                .map_or("Failed to get the track.", |reason| reason.user_message());
+            interaction_error_edit(message, interaction, ctx).await;
+            None
         }
-    };
-
-    let source_metadata = source.metadata.clone();
+    }
+}
 
-    // Queue the track
-    let mut call = call_lock.lock().await;
-    let (mut track, track_handle) = create_player(source);
-    let guild_id_str = interaction.guild_id.unwrap().0.to_string();
+/// The response for a queued track, the footer says what else is on its way.
+fn queued_embed(metadata: Metadata, position: usize, footer: Option<String>) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed.title(format!("Queued Track: {}", position));
+    embed.description(format!(
+        "[{}]({})",
+        metadata.title.unwrap_or("NONE".to_string()),
+        metadata.source_url.unwrap_or("NONE".to_string())
+    ));
+    if let Some(footer) = footer {
+        embed.footer(|f| f.text(footer));
+    }
+    // The track starts right away, but every ffmpeg slot is taken so it waits for one first.
+    if position == 1 && governor().is_saturated() {
+        let usage = governor().usage();
+        embed.field(
+            "Bot is busy",
+            format!(
+                "{} of {} audio slots are in use with {} waiting, this track is queued and starts once one frees up.",
+                usage.active, usage.max, usage.waiting
+            ),
+            false,
+        );
+    }
+    if let Some(thumbnail) = metadata.thumbnail {
+        embed.image(thumbnail);
+    }
+    embed
+}
 
-    // Try to get the guild from the database, returns an option if the guild was found.
-    let guild_doc = match get_guild_doc(mongo_client, guild_id_str, interaction, ctx).await {
-        Some(value) => value,
-        None => return,
+/// Resolves and queues the first pending entry on the call, the others are resolved one at a time
+/// as the tracks before them end. Returns the metadata of the first track and its queue position.
+pub async fn queue_entries(
+    ctx: &Context,
+    call_lock: &Arc<serenity::prelude::Mutex<Call>>,
+    pending: Arc<PendingEntries>,
+    volume: f32,
+    requester: UserId,
+    guild_id: GuildId,
+    db: &Db,
+) -> songbird::input::error::Result<(Metadata, usize)> {
+    let (first_entry, prefetched) = pending.next().unwrap_or_default();
+    let source = match prefetched {
+        Some(source) => source,
+        None => resolve(&first_entry, guild_id, db).await?,
     };
+    let source_metadata = *source.input.metadata.clone();
 
-    track.set_volume(guild_doc.volume);
-
-    call.enqueue(track);
-    if let QueryType::Playlist = query_type {
+    // Queue the track
+    let mut call = call_lock.lock().await;
+    let track_handle = enqueue(&mut call, source, volume, requester, guild_id, db).await;
+    if pending.has_more() {
+        guild_state(ctx, guild_id)
+            .await
+            .add_playlist(pending.clone())
+            .await;
+        pending.watch(&track_handle);
         call.add_global_event(
             Event::Track(TrackEvent::End),
             SongEndNotifier {
-                playlist: Mutex::new(playlist),
-                manager,
-                guild_id: guild.id,
+                pending,
+                manager: songbird::get(ctx)
+                    .await
+                    .expect("Songbird Voice client placed in at initialisation.")
+                    .clone(),
+                http: ctx.http.clone(),
+                guild_id,
+                volume,
+                requester,
+                db: db.clone(),
             },
         );
     }
-    let position: usize = call.queue().len();
+    Ok((source_metadata, call.queue().len()))
+}
 
-    // Send the response
-    info!("Creating response...");
-    let _res = interaction
-        .edit_original_interaction_response(&ctx.http, |message| {
-            message.embed(|embed| {
-                embed.title(format!("Queued Track: {}", position));
-                embed.description(format!(
-                    "[{}]({})",
-                    source_metadata.title.unwrap_or("NONE".to_string()),
-                    source_metadata.source_url.unwrap_or("NONE".to_string())
-                ));
-                embed.image(source_metadata.thumbnail.unwrap())
-            })
+/// Suggests the user's liked tracks that match what they typed so far, then earlier searches
+/// that start with it.
+pub async fn autocomplete(ctx: &Context, autocomplete: &AutocompleteInteraction, db: &Db) {
+    let typed = autocomplete
+        .data
+        .options
+        .iter()
+        .find(|option| option.focused)
+        .and_then(|option| option.value.as_ref())
+        .and_then(|value| value.as_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let liked = match liked_tracks(db, autocomplete.user.id).await {
+        Ok(liked) => liked,
+        Err(err) => {
+            error!("{:?}", err);
+            vec![]
+        }
+    };
+
+    let mut choices: Vec<(String, String)> = liked
+        .into_iter()
+        .filter(|track| track.title.to_lowercase().contains(&typed))
+        .map(|track| (track.title, track.url))
+        .collect();
+
+    // Searches that were resolved before, matched by how they start.
+    if !normalize_query(&typed).is_empty() {
+        match cached_metadata_by_prefix(db, &search_key(&typed), 10).await {
+            Ok(entries) => {
+                for entry in entries {
+                    let info: serde_json::Value = match serde_json::from_str(&entry.json) {
+                        Ok(info) => info,
+                        Err(_) => continue,
+                    };
+                    if let (Some(title), Some(url)) = (
+                        info.get("title").and_then(|title| title.as_str()),
+                        info.get("webpage_url").and_then(|url| url.as_str()),
+                    ) {
+                        if !choices.iter().any(|(_, known)| known == url) {
+                            choices.push((title.to_string(), url.to_string()));
+                        }
+                    }
+                }
+            }
+            Err(err) => error!("{:?}", err),
+        }
+    }
+
+    let res = autocomplete
+        .create_autocomplete_response(&ctx.http, |response| {
+            choices
+                .iter()
+                // Discord caps both the choice name and value at 100 characters.
+                .filter(|(_, url)| url.len() <= 100)
+                .take(25)
+                .for_each(|(title, url)| {
+                    let name: String = title.chars().take(100).collect();
+                    response.add_string_choice(name, url);
+                });
+            response
         })
         .await;
-    info!("Response created.");
+    if let Err(err) = res {
+        error!("{}", err);
+    }
 }
 
 #[allow(dead_code)]
@@ -177,6 +347,7 @@ pub async fn register(ctx: &Context) {
                     opt.name("song")
                         .description("A URL or search query.")
                         .kind(CommandOptionType::String)
+                        .set_autocomplete(true)
                         .required(true)
                 })
         })
@@ -188,37 +359,107 @@ pub async fn register(ctx: &Context) {
 }
 
 struct SongEndNotifier {
-    playlist: Mutex<Vec<Metadata>>,
+    pending: Arc<PendingEntries>,
     manager: Arc<Songbird>,
+    http: Arc<Http>,
     guild_id: GuildId,
+    volume: f32,
+    requester: UserId,
+    db: Db,
 }
 
 #[async_trait]
 impl EventHandler for SongEndNotifier {
-    async fn act(&self, _stx: &EventContext<'_>) -> Option<Event> {
-        let video_id = match self.playlist.lock().unwrap().pop() {
-            Some(meta) => match meta.source_url {
-                Some(url_str) => url_str,
-                None => return None,
-            },
-            None => return None,
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        // A track brought back with /back was put in front of the queue, it doesn't take the
+        // place of a playlist entry.
+        if let EventContext::Track(tracks) = ctx {
+            for (_, track_handle) in tracks.iter() {
+                if track_handle
+                    .typemap()
+                    .read()
+                    .await
+                    .contains_key::<TrackReplayed>()
+                {
+                    return None;
+                }
+            }
+        }
+
+        // A playlist that is still loading may not have listed the next entry yet.
+        self.pending.wait().await;
+
+        let call_lock = self.manager.get(self.guild_id)?;
+        let voice_channel_id = call_lock
+            .lock()
+            .await
+            .current_channel()
+            .map(|channel| ChannelId(channel.0));
+        let channel_id = notice_channel(&self.db, self.guild_id, voice_channel_id).await;
+
+        // Entries that fail to resolve are reported and passed over, a playlist that is broken
+        // throughout is dropped instead of being worked through one notice at a time. The call
+        // stays unlocked meanwhile, resolving can take a while.
+        let mut failures = 0;
+        let input = loop {
+            let (query, prefetched) = self.pending.next()?;
+            let res = match prefetched {
+                Some(source) => Ok(source),
+                None => resolve(&query, self.guild_id, &self.db).await,
+            };
+            match res {
+                Ok(input) => break input,
+                Err(err) => {
+                    let reason = FailureReason::from_input_error(&err);
+                    warn!("Playlist entry {} failed: {:?}. {}", query, reason, err);
+                    report(&self.http, channel_id, &query, &reason, false).await;
+                    failures += 1;
+                    if failures >= MAX_CONSECUTIVE_FAILURES {
+                        self.pending.clear();
+                        give_up(&self.http, channel_id).await;
+                        return None;
+                    }
+                }
+            }
         };
-        let call_lock = self.manager.get(self.guild_id).unwrap();
+
         let mut call = call_lock.lock().await;
+        // Skipped, left or replaced while the entry resolved.
+        if self.pending.is_cancelled() {
+            return None;
+        }
         let _ = call.queue().pause();
-        let input = ytdl(video_id).await.unwrap();
-        call.enqueue_source(input);
+        let track_handle = enqueue(
+            &mut call,
+            input,
+            self.volume,
+            self.requester,
+            self.guild_id,
+            &self.db,
+        )
+        .await;
         call.queue().modify_queue(|queue| {
             // Make sure that the first
             queue.swap(0, queue.len() - 1)
         });
         let _ = call.queue().resume();
+        self.pending.watch(&track_handle);
         None
     }
 }
 
+// The handler is dropped when the call's events are cleared, e.g. when the playlist is skipped or
+// the bot leaves, so the prefetched entries and their streams go with it.
+impl Drop for SongEndNotifier {
+    fn drop(&mut self) {
+        self.pending.cancel();
+    }
+}
+
 struct PlaylistError {
     cause: String,
+    /// Set when yt-dlp itself failed.
+    reason: Option<YtdlError>,
 }
 
 impl Display for PlaylistError {
@@ -227,54 +468,296 @@ impl Display for PlaylistError {
     }
 }
 
-async fn ytdl_playlist(uri: &str) -> Result<Vec<Metadata>, PlaylistError> {
-    let ytdl_args = [
-        "--print-json",
-        "--flat-playlist",
-        "--ignore-config",
-        "--no-warnings",
-        uri,
-    ];
-
-    let youtube_dl_res = TokioCommand::new("yt-dlp")
-        .args(ytdl_args)
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await;
+impl PlaylistError {
+    /// yt-dlp couldn't be run, or it was killed after the timeout.
+    fn io(err: std::io::Error) -> PlaylistError {
+        PlaylistError {
+            reason: (err.kind() == std::io::ErrorKind::TimedOut).then_some(YtdlError::Network),
+            cause: err.to_string(),
+        }
+    }
 
-    let youtube_dl_output = match youtube_dl_res {
-        Ok(output) => match from_utf8(&output.stdout) {
-            Ok(val) => val.to_owned(),
-            Err(e) => {
-                return Err(PlaylistError {
-                    cause: e.to_string(),
-                })
+    /// What to tell the user who asked for the playlist.
+    fn user_message(&self) -> &'static str {
+        match self.reason {
+            Some(YtdlError::Unknown) | None => "Failed to get the playlist.",
+            Some(reason) => reason.user_message(),
+        }
+    }
+}
+
+/// How often the response is edited while a playlist is listed.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Interaction tokens last 15 minutes, past this the progress is posted in the channel instead.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(14 * 60);
+
+/// Queues a playlist. A recently listed playlist is queued from the metadata cache, otherwise the
+/// entries are read as yt-dlp prints them: the first one starts playing right away, and the
+/// response counts the others as they come in.
+async fn load_playlist(
+    ctx: &Context,
+    interaction: &ApplicationCommandInteraction,
+    db: &Db,
+    uri: &str,
+) {
+    let key = playlist_key(uri);
+    let target = uri.to_string();
+    let mut refresh = Some(move || async move { fetch_playlist(&target).await });
+    if let Some(listing) = cached_entry(db, key.clone(), PLAYLIST_TTL, &mut refresh).await {
+        match playlist_urls(&listing) {
+            Ok(entries) => {
+                play_entries(ctx, interaction, db, entries).await;
+                return;
             }
-        },
-        Err(e) => {
-            return Err(PlaylistError {
-                cause: e.to_string(),
-            })
+            Err(err) => warn!("Listing {} again. {}", uri, err),
+        }
+    }
+
+    let extractor = extractor();
+    let mut lines = match extractor
+        .run_lines(
+            &["--print-json", "--flat-playlist", uri],
+            extractor.config.playlist_timeout(),
+        )
+        .await
+    {
+        Ok(lines) => lines,
+        Err(err) => {
+            let failure = PlaylistError::io(err);
+            error!("{}", failure);
+            interaction_error_edit(failure.user_message(), interaction, ctx).await;
+            return;
         }
     };
 
-    let mut metadata_vec = vec![];
+    let mut progress = Progress::new(ctx, interaction);
+    let mut listing = String::new();
+    let mut total: Option<u64> = None;
+    let mut loaded: u64 = 0;
+    let mut first_track: Option<(Metadata, usize)> = None;
+    let pending = PendingEntries::loading(interaction.guild_id.unwrap(), db);
 
-    for el in youtube_dl_output.split('\n') {
+    let read_res = loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break Ok(()),
+            Err(err) => break Err(err),
+        };
+        let json: Value = match serde_json::from_str(&line) {
+            Ok(json) => json,
+            Err(err) => {
+                warn!("Skipping a playlist entry. {}", err);
+                continue;
+            }
+        };
+        total = total.or_else(|| json.get("playlist_count").and_then(Value::as_u64));
+        let url = match Metadata::from_ytdl_output(json).source_url {
+            Some(url) => url,
+            None => continue,
+        };
+        listing.push_str(&line);
+        listing.push('\n');
+        loaded += 1;
+
+        pending.push(url);
+        if first_track.is_none() {
+            first_track = start_playback(ctx, interaction, db, pending.clone()).await;
+            if first_track.is_none() {
+                // The error was already reported, dropping the lines stops yt-dlp.
+                pending.cancel();
+                return;
+            }
+        }
+
+        let embed = progress_embed(&first_track, loading_text(loaded, total));
+        progress.show(embed, false).await;
+    };
+    pending.done_loading();
+
+    let finished = match read_res {
+        Ok(()) => lines.finish().await,
+        Err(err) => Err(err),
+    };
+    let failure = match finished {
+        Ok((status, _)) if status.success() => None,
+        Ok((status, stderr)) => Some(PlaylistError {
+            reason: Some(YtdlError::parse(status.code(), &stderr)),
+            cause: stderr.trim().to_string(),
+        }),
+        Err(err) => Some(PlaylistError::io(err)),
+    };
+
+    let footer = match (&failure, &first_track) {
+        (None, _) => {
+            store(db, &key, &listing).await;
+            format!("{} more tracks will be queued.", loaded.saturating_sub(1))
+        }
+        (Some(failure), Some(_)) => {
+            error!("{}", failure);
+            format!(
+                "Only {} tracks could be listed. {}",
+                loaded,
+                failure.user_message()
+            )
+        }
+        (Some(failure), None) => {
+            error!("{}", failure);
+            interaction_error_edit(failure.user_message(), interaction, ctx).await;
+            return;
+        }
+    };
+    if first_track.is_none() {
+        interaction_error_edit("There was nothing to play.", interaction, ctx).await;
+        return;
+    }
+    progress
+        .show(progress_embed(&first_track, footer), true)
+        .await;
+}
+
+/// Reads the URLs of a playlist listing, one JSON object per line.
+fn playlist_urls(listing: &str) -> Result<Vec<String>, PlaylistError> {
+    let mut urls = vec![];
+    for el in listing.split('\n') {
         if el.is_empty() {
             continue;
         }
-        let meta = match serde_json::from_str(&el.replace('\n', "")) {
+        let meta = match serde_json::from_str(el) {
             Ok(json) => Metadata::from_ytdl_output(json),
             Err(e) => {
                 return Err(PlaylistError {
                     cause: e.to_string(),
+                    reason: None,
                 })
             }
         };
-        metadata_vec.push(meta);
+        urls.extend(meta.source_url);
+    }
+    Ok(urls)
+}
+
+fn loading_text(loaded: u64, total: Option<u64>) -> String {
+    match total {
+        Some(total) => format!("Loaded {}/{}…", loaded, total),
+        None => format!("Loaded {}…", loaded),
     }
-    Ok(metadata_vec)
+}
+
+/// The queued first track with the progress below it, or just the progress until it is queued.
+fn progress_embed(first_track: &Option<(Metadata, usize)>, text: String) -> CreateEmbed {
+    match first_track {
+        Some((metadata, position)) => queued_embed(metadata.clone(), *position, Some(text)),
+        None => {
+            let mut embed = CreateEmbed::default();
+            embed.title("Loading Playlist");
+            embed.description(text);
+            embed
+        }
+    }
+}
+
+/// Where the progress of a loading playlist is shown. That is the deferred response until its
+/// token runs out or an edit fails, then a message in the channel.
+struct Progress<'a> {
+    ctx: &'a Context,
+    interaction: &'a ApplicationCommandInteraction,
+    started: Instant,
+    last_shown: Option<Instant>,
+    channel_message: Option<Message>,
+}
+
+impl<'a> Progress<'a> {
+    fn new(ctx: &'a Context, interaction: &'a ApplicationCommandInteraction) -> Progress<'a> {
+        Progress {
+            ctx,
+            interaction,
+            started: Instant::now(),
+            last_shown: None,
+            channel_message: None,
+        }
+    }
+
+    /// Shows the embed, at most once per `PROGRESS_INTERVAL` unless forced.
+    async fn show(&mut self, embed: CreateEmbed, force: bool) {
+        if !force
+            && self
+                .last_shown
+                .is_some_and(|shown| shown.elapsed() < PROGRESS_INTERVAL)
+        {
+            return;
+        }
+        self.last_shown = Some(Instant::now());
+
+        if let Some(message) = &mut self.channel_message {
+            let res = message
+                .edit(&self.ctx.http, |message| message.set_embed(embed))
+                .await;
+            if let Err(err) = res {
+                error!("Could not update the playlist progress. {}", err);
+            }
+            return;
+        }
+
+        if self.started.elapsed() < TOKEN_LIFETIME {
+            let res = self
+                .interaction
+                .edit_original_interaction_response(&self.ctx.http, |message| {
+                    message.set_embed(embed.clone())
+                })
+                .await;
+            match res {
+                Ok(_) => return,
+                Err(err) => warn!("Moving the playlist progress to the channel. {}", err),
+            }
+        }
+
+        let res = self
+            .interaction
+            .channel_id
+            .send_message(&self.ctx.http, |message| message.set_embed(embed))
+            .await;
+        match res {
+            Ok(message) => self.channel_message = Some(message),
+            Err(err) => error!("Could not send the playlist progress. {}", err),
+        }
+    }
+}
+
+/// Runs yt-dlp for the entries of a playlist, one JSON object per line.
+async fn fetch_playlist(uri: &str) -> Result<String, PlaylistError> {
+    let extractor = extractor();
+    let youtube_dl_res = extractor
+        .run(
+            &["--print-json", "--flat-playlist", uri],
+            extractor.config.playlist_timeout(),
+        )
+        .await;
+
+    let youtube_dl_output = match youtube_dl_res {
+        Ok(output) if !output.status.success() => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(PlaylistError {
+                reason: Some(YtdlError::parse(output.status.code(), &stderr)),
+                cause: stderr
+                    .lines()
+                    .rev()
+                    .find(|line| !line.trim().is_empty())
+                    .unwrap_or("yt-dlp failed")
+                    .to_string(),
+            });
+        }
+        Ok(output) => match from_utf8(&output.stdout) {
+            Ok(val) => val.to_owned(),
+            Err(e) => {
+                return Err(PlaylistError {
+                    cause: e.to_string(),
+                    reason: None,
+                })
+            }
+        },
+        Err(e) => return Err(PlaylistError::io(e)),
+    };
+
+    Ok(youtube_dl_output)
 }
@@ -1,15 +1,44 @@
-use serenity::model::application::command::Command;
-
+use serde::{Deserialize, Serialize};
+use serenity::model::application::command::{Command, CommandOptionType};
+use serenity::model::channel::AttachmentType;
 use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::model::prelude::Attachment;
 use serenity::prelude::Context;
-use tracing::{error, info};
+use std::borrow::Cow;
+use tracing::{error, info, warn};
+
+use crate::commands::common::interaction_error::{
+    channel_message_error, interaction_error, interaction_error_edit,
+};
+use crate::commands::common::slash_commands::{extract_vec, get_attachment, get_string};
+use crate::commands::music::play::play_entries;
+use crate::mongo_conn::Db;
+use crate::player::join;
+
+const MAX_IMPORT_BYTES: u64 = 1024 * 1024;
+/// Entries past this are left out of an import.
+const MAX_IMPORT_ENTRIES: usize = 500;
 
 #[allow(unused)]
-pub async fn command(
-    ctx: &Context,
-    interaction: &ApplicationCommandInteraction,
-    mongo_client: &mongodb::Client,
-) {
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
+    let subcommand = match interaction.data.options.first() {
+        Some(subcommand) => subcommand,
+        None => {
+            interaction_error("No subcommand was given.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    match subcommand.name.as_str() {
+        "show" => show(ctx, interaction, db).await,
+        "export" => export(ctx, interaction, &subcommand.options).await,
+        "import" => import(ctx, interaction, db, &subcommand.options).await,
+        _ => interaction_error("Unknown subcommand.", interaction, ctx).await,
+    }
+}
+
+#[allow(unused)]
+async fn show(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
     interaction
         .create_interaction_response(&ctx.http, |response| {
             response.interaction_response_data(|message| message.ephemeral(true));
@@ -17,33 +46,36 @@ pub async fn command(
         })
         .await;
 
-    let guild = interaction
+    let guild = match interaction
         .guild_id
-        .unwrap()
-        .to_guild_cached(&ctx.cache)
-        .unwrap();
-    let voice_state = guild.voice_states.get(&interaction.user.id).unwrap();
-    let vc = voice_state.channel_id.unwrap();
-    let vc_name = vc.name(&ctx.cache).await.unwrap();
+        .and_then(|guild_id| guild_id.to_guild_cached(&ctx.cache))
+    {
+        Some(guild) => guild,
+        None => {
+            interaction_error_edit("This command must be run in a guild.", interaction, ctx).await;
+            return;
+        }
+    };
+    let vc = match guild
+        .voice_states
+        .get(&interaction.user.id)
+        .and_then(|voice_state| voice_state.channel_id)
+    {
+        Some(vc) => vc,
+        None => {
+            interaction_error_edit("Join a voice channel first.", interaction, ctx).await;
+            return;
+        }
+    };
 
     // Get the call
     let manager = songbird::get(ctx)
         .await
         .expect("Songbird Voice client placed in at initialisation.")
         .clone();
-    let guild = interaction
-        .guild_id
-        .unwrap()
-        .to_guild_cached(&ctx.cache)
-        .unwrap();
     let call_lock = match manager.get(guild.id) {
         Some(ongoing_call) => ongoing_call,
-        None => {
-            let voice_state = guild.voice_states.get(&interaction.user.id).unwrap();
-            let vc = voice_state.channel_id.unwrap();
-            let vc_name = vc.name(&ctx.cache).await.unwrap();
-            manager.join(guild.id, vc).await.0
-        }
+        None => join(ctx, guild.id, vc, db).await.0,
     };
 
     let call = call_lock.lock().await;
@@ -53,24 +85,18 @@ pub async fn command(
         .edit_original_interaction_response(&ctx.http, |message| {
             message.embed(|embed| {
                 embed.title("Current Queue");
-                for (count, track) in call.queue().current_queue().iter().enumerate() {
-                    if count > 25 {
-                        break;
-                    }
-                    let title = track.metadata().title.clone().unwrap();
-                    let source_url = track.metadata().source_url.clone().unwrap();
+                // An embed holds at most 25 fields.
+                for (count, track) in call.queue().current_queue().iter().enumerate().take(25) {
+                    let metadata = track.metadata();
+                    let title = metadata.title.clone().unwrap_or("Unknown".to_string());
+                    let entry = match &metadata.source_url {
+                        Some(source_url) => format!("[{}]({})", title, source_url),
+                        None => title,
+                    };
                     if count == 0 {
-                        embed.field(
-                            "Currently Playing",
-                            format!("[{}]({})", title, source_url),
-                            false,
-                        );
+                        embed.field("Currently Playing", entry, false);
                     } else {
-                        embed.field(
-                            (count + 1).to_string(),
-                            format!("[{}]({})", title, source_url),
-                            false,
-                        );
+                        embed.field((count + 1).to_string(), entry, false);
                     }
                 }
                 embed
@@ -80,16 +106,256 @@ pub async fn command(
         })
         .await;
     info!("Response created.");
-    
 }
+async fn export(
+    ctx: &Context,
+    interaction: &ApplicationCommandInteraction,
+    options: &[CommandDataOption],
+) {
+    let mut format_opt: Option<String> = None;
+    for tup in extract_vec(options).await {
+        if tup.0 == "format" {
+            if let Some(x) = get_string(tup.1) {
+                format_opt = Some(x);
+            } else {
+                interaction_error("'format' param was invalid.", interaction, ctx).await;
+                return;
+            }
+        }
+    }
+    let format = format_opt.unwrap_or_else(|| "json".to_string());
+
+    let guild_id = match interaction.guild_id {
+        Some(id) => id,
+        None => {
+            interaction_error("This command must be run in a guild.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+    let call_lock = match manager.get(guild_id) {
+        Some(call_lock) => call_lock,
+        None => {
+            interaction_error("Bot is not in a voice chat.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let tracks: Vec<ExportedTrack> = call_lock
+        .lock()
+        .await
+        .queue()
+        .current_queue()
+        .iter()
+        .filter_map(|track| {
+            let metadata = track.metadata();
+            Some(ExportedTrack {
+                title: metadata.title.clone().unwrap_or_default(),
+                url: metadata.source_url.clone()?,
+                duration_secs: metadata.duration.map(|duration| duration.as_secs()),
+            })
+        })
+        .collect();
+
+    if tracks.is_empty() {
+        interaction_error("The queue is empty.", interaction, ctx).await;
+        return;
+    }
+
+    let contents = match format.as_str() {
+        "m3u" => {
+            let mut contents = "#EXTM3U\n".to_string();
+            for track in &tracks {
+                contents.push_str(&format!(
+                    "#EXTINF:{},{}\n{}\n",
+                    track.duration_secs.map(|secs| secs as i64).unwrap_or(-1),
+                    track.title,
+                    track.url
+                ));
+            }
+            contents
+        }
+        "txt" => tracks
+            .iter()
+            .map(|track| format!("{}\n", track.url))
+            .collect(),
+        _ => match serde_json::to_string_pretty(&tracks) {
+            Ok(json) => json,
+            Err(err) => {
+                error!("{}", err);
+                interaction_error("Could not export the queue.", interaction, ctx).await;
+                return;
+            }
+        },
+    };
+
+    info!("Creating response...");
+    let res = interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message.content(format!("Exported {} tracks.", tracks.len()));
+                    message.add_file(AttachmentType::Bytes {
+                        data: Cow::from(contents.into_bytes()),
+                        filename: format!("queue.{}", format),
+                    })
+                })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+        channel_message_error("Could not send the exported queue.", interaction, ctx).await;
+    } else {
+        info!("Response created.");
+    }
+}
+
+async fn import(
+    ctx: &Context,
+    interaction: &ApplicationCommandInteraction,
+    db: &Db,
+    options: &[CommandDataOption],
+) {
+    let _res = interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await;
+
+    let mut attachment_opt: Option<Attachment> = None;
+    for tup in extract_vec(options).await {
+        if tup.0 == "attachment" {
+            if let Some(x) = get_attachment(tup.1) {
+                attachment_opt = Some(x);
+            } else {
+                interaction_error_edit("'attachment' param was invalid.", interaction, ctx).await;
+                return;
+            }
+        }
+    }
+
+    let attachment = match attachment_opt {
+        Some(attachment) => attachment,
+        None => {
+            interaction_error_edit("'attachment' param was missing.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    if attachment.size > MAX_IMPORT_BYTES {
+        interaction_error_edit("The file is too large to import.", interaction, ctx).await;
+        return;
+    }
+
+    let contents = match attachment.download().await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+        Err(err) => {
+            error!("{}", err);
+            interaction_error_edit("Could not download the file.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let mut entries = parse_queue_file(&contents);
+    if entries.is_empty() {
+        interaction_error_edit("The file did not contain any tracks.", interaction, ctx).await;
+        return;
+    }
+    if entries.len() > MAX_IMPORT_ENTRIES {
+        warn!(
+            "Importing the first {} of {} entries.",
+            MAX_IMPORT_ENTRIES,
+            entries.len()
+        );
+        entries.truncate(MAX_IMPORT_ENTRIES);
+    }
+
+    play_entries(ctx, interaction, db, entries).await;
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedTrack {
+    title: String,
+    url: String,
+    duration_secs: Option<u64>,
+}
+
+/// Reads the entries out of a JSON, M3U or plain text queue file. JSON may be a list of exported
+/// tracks or a list of strings, everything else is read as one URL or search query per line.
+fn parse_queue_file(contents: &str) -> Vec<String> {
+    let trimmed = contents.trim_start_matches('\u{feff}').trim();
+    if trimmed.starts_with('[') {
+        if let Ok(values) = serde_json::from_str::<Vec<serde_json::Value>>(trimmed) {
+            return values
+                .iter()
+                .filter_map(|value| match value {
+                    serde_json::Value::String(entry) => Some(entry.trim().to_string()),
+                    serde_json::Value::Object(track) => track
+                        .get("url")
+                        .or_else(|| track.get("title"))
+                        .and_then(|entry| entry.as_str())
+                        .map(|entry| entry.trim().to_string()),
+                    _ => None,
+                })
+                .filter(|entry| !entry.is_empty())
+                .collect();
+        }
+    }
+
+    trimmed
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
 #[allow(dead_code)]
 pub async fn register(ctx: &Context) {
     if let Err(err) = Command::create_global_application_command(&*ctx.http, |command| {
-        command.name("queue").description("The current queue.")
+        command
+            .name("queue")
+            .description("The current queue.")
+            .create_option(|opt| {
+                opt.name("show")
+                    .description("Shows the current queue.")
+                    .kind(CommandOptionType::SubCommand)
+            })
+            .create_option(|opt| {
+                opt.name("export")
+                    .description("Uploads the current queue as a file.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|sub_opt| {
+                        sub_opt
+                            .name("format")
+                            .description("The file format.")
+                            .kind(CommandOptionType::String)
+                            .add_string_choice("JSON", "json")
+                            .add_string_choice("M3U", "m3u")
+                            .add_string_choice("Plain text", "txt")
+                    })
+            })
+            .create_option(|opt| {
+                opt.name("import")
+                    .description("Queues the tracks from an exported queue or a list of URLs.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|sub_opt| {
+                        sub_opt
+                            .name("attachment")
+                            .description("A JSON, M3U or text file.")
+                            .kind(CommandOptionType::Attachment)
+                            .required(true)
+                    })
+            })
     })
     .await
     {
-        error!("Could not register join command! {}", err.to_string());
+        error!("Could not register queue command! {}", err.to_string());
         panic!()
     }
 }
@@ -6,12 +6,16 @@ use tracing::{error, info};
 
 use crate::commands::common::interaction_error::interaction_error_edit;
 use crate::commands::common::slash_commands::extract_vec;
+use crate::mongo_conn::Db;
+use crate::player::guild_state::guild_state;
+use crate::player::previous;
+use crate::player::{register_call_events, TrackSkipped};
 
 #[allow(unused)]
 pub async fn command(
     ctx: &Context,
     interaction: &ApplicationCommandInteraction,
-    mongo_client: &mongodb::Client,
+    db: &Db,
 ) {
     let guild = interaction
         .guild_id
@@ -41,7 +45,7 @@ pub async fn command(
     if let Some(handler_lock) = manager.get(guild.id) {
         let mut handler = handler_lock.lock().await;
         let queue = handler.queue().current_queue();
-        let track_handle = match queue.get(0) {
+        let track_handle = match queue.first() {
             None => {
                 info!("Creating response...");
                 let _res = interaction
@@ -72,8 +76,26 @@ pub async fn command(
         // Clear the events
         if bypass_playlist {
             handler.remove_all_global_events();
+            let previous = previous::stacks(ctx).await;
+            let state = guild_state(ctx, guild.id).await;
+            state.cancel_playlists().await;
+            register_call_events(
+                &mut handler,
+                &manager,
+                &ctx.http,
+                guild.id,
+                db,
+                &previous,
+                &state,
+            );
         }
 
+        track_handle
+            .typemap()
+            .write()
+            .await
+            .insert::<TrackSkipped>(true);
+
         if let Err(track_error) = handler.queue().skip() {
             error!("{}", track_error.to_string());
             interaction_error_edit("Failed to skip song!", interaction, ctx);
@@ -0,0 +1,407 @@
+use mongodb::bson::{doc, Bson, DateTime, Document};
+use mongodb::Collection;
+use serenity::builder::CreateEmbed;
+use serenity::futures::TryStreamExt;
+use serenity::model::application::command::Command;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::model::user::User;
+use serenity::prelude::Context;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info};
+
+use crate::commands::common::interaction_error::interaction_error_edit;
+use crate::commands::common::slash_commands::{extract_vec, get_string, get_user};
+use crate::dbmodels::history::HistoryEntry;
+use crate::mongo_conn::Db;
+
+const TOP_COUNT: i32 = 5;
+const CHART_WIDTH: i64 = 20;
+const CHART_DAYS: usize = 14;
+/// Keeps the top lists within the 1024 characters of an embed field, five entries each.
+const MAX_NAME_CHARS: usize = 70;
+const MAX_LINK_CHARS: usize = 100;
+
+#[allow(unused)]
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
+    interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await;
+
+    let guild_id_str = match interaction.guild_id {
+        Some(id) => id.0.to_string(),
+        None => {
+            interaction_error_edit("This command must be run in a guild.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let subcommand = match interaction.data.options.first() {
+        Some(subcommand) => subcommand,
+        None => {
+            interaction_error_edit("No subcommand was given.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let mut period_opt: Option<String> = None;
+    let mut member_opt: Option<User> = None;
+    for tup in extract_vec(&subcommand.options).await {
+        if tup.0 == "period" {
+            if let Some(x) = get_string(tup.1) {
+                period_opt = Some(x);
+            } else {
+                interaction_error_edit("'period' param was invalid.", interaction, ctx).await;
+                return;
+            }
+        } else if tup.0 == "member" {
+            if let Some(x) = get_user(tup.1) {
+                member_opt = Some(x);
+            } else {
+                interaction_error_edit("'member' param was invalid.", interaction, ctx).await;
+                return;
+            }
+        }
+    }
+
+    let period = period_opt.unwrap_or_else(|| "30d".to_string());
+    let since_opt: Option<DateTime> = match period.as_str() {
+        "7d" => Some(days_ago(7)),
+        "30d" => Some(days_ago(30)),
+        "all" => None,
+        _ => {
+            interaction_error_edit("'period' param was invalid.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let mut filter = doc! {"guild_ID": &guild_id_str};
+    if let Some(since) = since_opt {
+        filter.insert("started_at", doc! {"$gte": since});
+    }
+
+    let title = match subcommand.name.as_str() {
+        "guild" => "Server Listening Stats".to_string(),
+        "user" => {
+            let member = match member_opt {
+                Some(member) => member,
+                None => {
+                    interaction_error_edit("'member' param was missing.", interaction, ctx).await;
+                    return;
+                }
+            };
+            filter.insert("requester_ID", member.id.0.to_string());
+            format!("Listening Stats for {}", member.name)
+        }
+        _ => {
+            interaction_error_edit("Unknown subcommand.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let stats = match aggregate_stats(db, filter).await {
+        Ok(stats) => stats,
+        Err(err) => {
+            error!("{:?}", err);
+            interaction_error_edit("Could not compute the stats.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let mut embed = stats_embed(&stats, subcommand.name == "guild");
+    embed.title(title);
+    embed.footer(|footer| footer.text(format!("Period: {} - Times are in UTC.", period)));
+
+    info!("Creating response...");
+    let res = interaction
+        .edit_original_interaction_response(&ctx.http, |message| message.set_embed(embed))
+        .await;
+    if let Err(err) = res {
+        error!("Could not send the stats. {}", err);
+        return;
+    }
+    info!("Response created.");
+}
+
+/// Runs a single `$facet` pipeline over the history so each stat is one pass over the index.
+async fn aggregate_stats(db: &Db, filter: Document) -> mongodb::error::Result<Document> {
+    let collection: Collection<HistoryEntry> = db.history();
+
+    let pipeline = vec![
+        doc! {"$match": filter},
+        doc! {"$facet": {
+            "top_tracks": [
+                {"$group": {"_id": "$url", "title": {"$first": "$title"}, "plays": {"$sum": 1}}},
+                {"$sort": {"plays": -1}},
+                {"$limit": TOP_COUNT},
+            ],
+            "top_artists": [
+                {"$match": {"artist": {"$ne": null}}},
+                {"$group": {"_id": "$artist", "plays": {"$sum": 1}}},
+                {"$sort": {"plays": -1}},
+                {"$limit": TOP_COUNT},
+            ],
+            "top_requesters": [
+                {"$group": {"_id": "$requester_ID", "plays": {"$sum": 1}}},
+                {"$sort": {"plays": -1}},
+                {"$limit": TOP_COUNT},
+            ],
+            "totals": [
+                {"$group": {
+                    "_id": null,
+                    "plays": {"$sum": 1},
+                    "skips": {"$sum": {"$cond": ["$skipped", 1, 0]}},
+                    "listened_ms": {"$sum": {"$cond": [
+                        {"$gt": ["$ended_at", null]},
+                        {"$subtract": ["$ended_at", "$started_at"]},
+                        0,
+                    ]}},
+                }},
+            ],
+            "busiest_hours": [
+                {"$group": {"_id": {"$hour": "$started_at"}, "plays": {"$sum": 1}}},
+                {"$sort": {"plays": -1}},
+                {"$limit": 3},
+            ],
+            "per_day": [
+                {"$group": {
+                    "_id": {"$dateToString": {"format": "%Y-%m-%d", "date": "$started_at"}},
+                    "plays": {"$sum": 1},
+                }},
+                {"$sort": {"_id": -1}},
+                {"$limit": CHART_DAYS as i32},
+            ],
+        }},
+    ];
+
+    let mut cursor = collection.aggregate(pipeline, None).await?;
+    Ok(cursor.try_next().await?.unwrap_or_default())
+}
+
+fn stats_embed(stats: &Document, show_requesters: bool) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+
+    let totals = facet(stats, "totals")
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    let plays = number(totals.get("plays"));
+    if plays == 0 {
+        embed.description("Nothing has been played in this period...");
+        return embed;
+    }
+    let skips = number(totals.get("skips"));
+    let listened = Duration::from_millis(number(totals.get("listened_ms")).max(0) as u64);
+
+    embed.field("Tracks Played", plays, true);
+    embed.field(
+        "Listening Time",
+        format!(
+            "{}h {}m",
+            listened.as_secs() / 3600,
+            (listened.as_secs() % 3600) / 60
+        ),
+        true,
+    );
+    embed.field(
+        "Skip Rate",
+        format!("{:.1}%", skips as f64 / plays as f64 * 100.0),
+        true,
+    );
+
+    let top_tracks: Vec<String> = facet(stats, "top_tracks")
+        .iter()
+        .enumerate()
+        .map(|(count, track)| {
+            let title = shorten(track.get_str("title").unwrap_or("Unknown"));
+            let url = track.get_str("_id").unwrap_or_default();
+            // A link that long is left out rather than cut, a cut link goes nowhere.
+            let name = if url.is_empty() || url.chars().count() > MAX_LINK_CHARS {
+                title
+            } else {
+                format!("[{}]({})", title, url)
+            };
+            format!(
+                "`{}.` {} - {} plays",
+                count + 1,
+                name,
+                number(track.get("plays"))
+            )
+        })
+        .collect();
+    embed.field("Top Tracks", list_or_none(top_tracks), false);
+
+    let top_artists: Vec<String> = facet(stats, "top_artists")
+        .iter()
+        .enumerate()
+        .map(|(count, artist)| {
+            format!(
+                "`{}.` {} - {} plays",
+                count + 1,
+                shorten(artist.get_str("_id").unwrap_or("Unknown")),
+                number(artist.get("plays"))
+            )
+        })
+        .collect();
+    embed.field("Top Artists", list_or_none(top_artists), false);
+
+    if show_requesters {
+        let top_requesters: Vec<String> = facet(stats, "top_requesters")
+            .iter()
+            .enumerate()
+            .map(|(count, requester)| {
+                format!(
+                    "`{}.` <@{}> - {} plays",
+                    count + 1,
+                    requester.get_str("_id").unwrap_or("0"),
+                    number(requester.get("plays"))
+                )
+            })
+            .collect();
+        embed.field("Top Requesters", list_or_none(top_requesters), false);
+    }
+
+    let busiest_hours: Vec<String> = facet(stats, "busiest_hours")
+        .iter()
+        .map(|hour| {
+            format!(
+                "{:02}:00 - {} plays",
+                number(hour.get("_id")),
+                number(hour.get("plays"))
+            )
+        })
+        .collect();
+    embed.field("Busiest Hours", list_or_none(busiest_hours), false);
+
+    let mut per_day: Vec<(String, i64)> = facet(stats, "per_day")
+        .iter()
+        .map(|day| {
+            (
+                day.get_str("_id").unwrap_or_default().to_string(),
+                number(day.get("plays")),
+            )
+        })
+        .collect();
+    per_day.reverse();
+    embed.field(
+        "Activity Per Day",
+        format!("```\n{}```", activity_chart(&per_day)),
+        false,
+    );
+
+    embed
+}
+
+/// Cuts a title or name down to `MAX_NAME_CHARS`.
+fn shorten(name: &str) -> String {
+    if name.chars().count() <= MAX_NAME_CHARS {
+        return name.to_string();
+    }
+    let mut short: String = name.chars().take(MAX_NAME_CHARS - 1).collect();
+    short.push('…');
+    short
+}
+
+/// Draws one bar per day, scaled so the busiest day fills the chart width.
+fn activity_chart(per_day: &[(String, i64)]) -> String {
+    let max = per_day
+        .iter()
+        .map(|(_, plays)| *plays)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let mut chart = String::new();
+    for (day, plays) in per_day {
+        let width = (plays * CHART_WIDTH + max - 1) / max;
+        chart.push_str(&format!(
+            "{} {:<width$} {}\n",
+            day.get(5..).unwrap_or(day),
+            "#".repeat(width as usize),
+            plays,
+            width = CHART_WIDTH as usize
+        ));
+    }
+    chart
+}
+
+fn facet(stats: &Document, name: &str) -> Vec<Document> {
+    match stats.get_array(name) {
+        Ok(values) => values
+            .iter()
+            .filter_map(|value| value.as_document().cloned())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+fn number(value: Option<&Bson>) -> i64 {
+    match value {
+        Some(Bson::Int32(num)) => *num as i64,
+        Some(Bson::Int64(num)) => *num,
+        Some(Bson::Double(num)) => *num as i64,
+        _ => 0,
+    }
+}
+
+fn list_or_none(lines: Vec<String>) -> String {
+    if lines.is_empty() {
+        "None".to_string()
+    } else {
+        lines.join("\n")
+    }
+}
+
+fn days_ago(days: u64) -> DateTime {
+    DateTime::from_system_time(SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60))
+}
+
+#[allow(dead_code)]
+pub async fn register(ctx: &Context) {
+    if let Err(err) = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("stats")
+            .description("Listening statistics from the play history.")
+            .create_option(|opt| {
+                opt.name("guild")
+                    .description("Stats for the whole server.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|sub_opt| {
+                        sub_opt
+                            .name("period")
+                            .description("The period to look at.")
+                            .kind(CommandOptionType::String)
+                            .add_string_choice("7 days", "7d")
+                            .add_string_choice("30 days", "30d")
+                            .add_string_choice("All time", "all")
+                    })
+            })
+            .create_option(|opt| {
+                opt.name("user")
+                    .description("Stats for the tracks a member requested.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|sub_opt| {
+                        sub_opt
+                            .name("member")
+                            .description("The member to look at.")
+                            .kind(CommandOptionType::User)
+                            .required(true)
+                    })
+                    .create_sub_option(|sub_opt| {
+                        sub_opt
+                            .name("period")
+                            .description("The period to look at.")
+                            .kind(CommandOptionType::String)
+                            .add_string_choice("7 days", "7d")
+                            .add_string_choice("30 days", "30d")
+                            .add_string_choice("All time", "all")
+                    })
+            })
+    })
+    .await
+    {
+        error!("Could not register stats command! {}", err.to_string());
+        panic!()
+    }
+}
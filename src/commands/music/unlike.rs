@@ -0,0 +1,59 @@
+use serenity::model::application::command::Command;
+use serenity::model::prelude::interaction::{application_command::*, InteractionResponseType};
+use serenity::prelude::Context;
+use tracing::{error, info};
+
+use crate::commands::common::interaction_error::interaction_error;
+use crate::mongo_conn::{unlike_track, Db};
+use crate::player::current_track;
+
+/// Removes the current track from the user's liked tracks.
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
+    let url = match current_track(ctx, interaction.guild_id)
+        .await
+        .and_then(|track_handle| track_handle.metadata().source_url.clone())
+    {
+        Some(url) => url,
+        None => {
+            interaction_error("There is nothing playing right now.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    let content = match unlike_track(db, interaction.user.id, &url).await {
+        Ok(true) => "Removed it from your liked tracks.",
+        Ok(false) => "This track isn't in your liked tracks.",
+        Err(err) => {
+            error!("{:?}", err);
+            interaction_error("Could not update the database.", interaction, ctx).await;
+            return;
+        }
+    };
+
+    info!("Creating response...");
+    let res = interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| message.ephemeral(true).content(content))
+        })
+        .await;
+    if let Err(err) = res {
+        error!("{}", err);
+    }
+    info!("Response created.");
+}
+
+#[allow(dead_code)]
+pub async fn register(ctx: &Context) {
+    if let Err(err) = Command::create_global_application_command(&*ctx.http, |command| {
+        command
+            .name("unlike")
+            .description("Removes the current track from your liked tracks.")
+    })
+    .await
+    {
+        error!("Could not register unlike command! {}", err.to_string());
+        panic!()
+    }
+}
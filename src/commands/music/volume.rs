@@ -9,13 +9,10 @@ use tracing::{debug, error, info};
 use crate::commands::common::interaction_error::interaction_error_edit;
 use crate::commands::common::slash_commands::extract_vec;
 use crate::dbmodels::guild::Guild as GuildStruct;
+use crate::mongo_conn::Db;
 
 #[allow(unused)]
-pub async fn command(
-    ctx: &Context,
-    interaction: &ApplicationCommandInteraction,
-    mongo_client: &mongodb::Client,
-) {
+pub async fn command(ctx: &Context, interaction: &ApplicationCommandInteraction, db: &Db) {
     interaction
         .create_interaction_response(&ctx.http, |response| {
             response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
@@ -90,8 +87,7 @@ pub async fn command(
         info!("Response created.");
 
         let guild_id_str = guild.id.0.to_string();
-        let collection: Collection<GuildStruct> =
-            mongo_client.database("botdb").collection("guilds");
+        let collection: Collection<GuildStruct> = db.guilds();
         let update_res = match collection
             .update_one(
                 doc! {"guild_ID": guild_id_str},
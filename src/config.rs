@@ -0,0 +1,653 @@
+use mongodb::options::ResolverConfig;
+use serde::Deserialize;
+use serenity::model::gateway::GatewayIntents;
+use serenity::prelude::{Context, TypeMapKey};
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use url::Url;
+
+use crate::player::ytdl::YtdlConfig;
+
+/// Where the config file is read from when `CONFIG_PATH` isn't set. It may be missing, then
+/// everything comes from the environment.
+const DEFAULT_PATH: &str = "config.toml";
+
+/// The settings of the bot, read from a TOML file with environment variables taking precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub discord: DiscordConfig,
+    pub mongo: MongoConfig,
+    pub log: LogConfig,
+    pub player: PlayerConfig,
+    pub ytdl: YtdlConfig,
+    pub cache: CacheConfig,
+    pub history: HistoryConfig,
+    pub sponsorblock: SponsorBlockConfig,
+    pub lyrics: LyricsConfig,
+    pub features: Features,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordConfig {
+    /// `DISCORD_TOKEN`
+    pub token: String,
+    /// `APPLICATION_ID`
+    pub application_id: u64,
+    /// `INTENTS`, comma separated. Names of `GatewayIntents` in lower case, or `all` and
+    /// `non_privileged`.
+    pub intents: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MongoConfig {
+    /// `MONGO_CONN_STR`
+    pub uri: String,
+    /// `DB_NAME`
+    pub db_name: String,
+    /// `MONGO_RESOLVER`, the DNS resolver used to look up `mongodb+srv://` hosts.
+    pub resolver: Resolver,
+}
+
+/// Where `mongodb+srv://` records are looked up. The system resolver can't do SRV lookups on
+/// some platforms, Windows among them, one of the public resolvers works there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolver {
+    System,
+    Cloudflare,
+    Google,
+    Quad9,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// `LOG_FORMAT`
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Json,
+    Pretty,
+    Compact,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerConfig {
+    /// `DEFAULT_VOLUME`, the volume new guilds start with.
+    pub default_volume: f32,
+    /// `EMPTY_CHANNEL_GRACE_SECS`, how long playback stays paused in an empty channel before the
+    /// bot leaves.
+    pub empty_channel_grace_secs: u64,
+    /// `FFMPEG_MAX_CONCURRENT`, how many ffmpeg pipelines run at once across all guilds.
+    pub ffmpeg_max_concurrent: usize,
+}
+
+/// The disk cache for played audio.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// `AUDIO_CACHE_DIR`, the cache is off without it.
+    pub dir: Option<String>,
+    /// `AUDIO_CACHE_MAX_MB`
+    pub max_mb: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// `HISTORY_RETENTION_DAYS`, how long played tracks stay in the history.
+    pub retention_days: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SponsorBlockConfig {
+    /// `SPONSORBLOCK_API_URL`, a SponsorBlock-compatible API.
+    pub api_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LyricsConfig {
+    /// `LYRICS_DIR`, a directory of `.lrc` and `.txt` files searched before the API.
+    pub dir: Option<String>,
+    /// `LYRICS_API_URL`, an LRCLIB-compatible API. Empty turns the API off.
+    pub api_url: String,
+}
+
+/// Parts of the bot that can be turned off for the whole deployment.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Features {
+    /// `FEATURE_AUDIO_CACHE`, the disk cache still needs `cache.dir` on top of this.
+    pub audio_cache: bool,
+    /// `FEATURE_SPONSORBLOCK`
+    pub sponsorblock: bool,
+    /// `FEATURE_ALWAYS_ON`, whether always-on channels are joined again after a restart.
+    pub always_on: bool,
+}
+
+impl Default for DiscordConfig {
+    fn default() -> Self {
+        DiscordConfig {
+            token: String::new(),
+            application_id: 0,
+            intents: vec!["all".to_string()],
+        }
+    }
+}
+
+impl Default for MongoConfig {
+    fn default() -> Self {
+        MongoConfig {
+            uri: String::new(),
+            db_name: "botdb".to_string(),
+            resolver: Resolver::System,
+        }
+    }
+}
+
+impl HistoryConfig {
+    /// How long played tracks are kept, the expiry of the TTL index on the history.
+    pub fn retention(&self) -> Duration {
+        Duration::from_secs(self.retention_days * 24 * 60 * 60)
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            format: LogFormat::Json,
+        }
+    }
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        PlayerConfig {
+            default_volume: 0.7,
+            empty_channel_grace_secs: 120,
+            ffmpeg_max_concurrent: 16,
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            dir: None,
+            max_mb: 1024,
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig { retention_days: 90 }
+    }
+}
+
+impl Default for SponsorBlockConfig {
+    fn default() -> Self {
+        SponsorBlockConfig {
+            api_url: "https://sponsor.ajay.app".to_string(),
+        }
+    }
+}
+
+impl Default for LyricsConfig {
+    fn default() -> Self {
+        LyricsConfig {
+            dir: None,
+            api_url: "https://lrclib.net".to_string(),
+        }
+    }
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features {
+            audio_cache: true,
+            sponsorblock: true,
+            always_on: true,
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "json" => Ok(LogFormat::Json),
+            "pretty" => Ok(LogFormat::Pretty),
+            "compact" => Ok(LogFormat::Compact),
+            _ => Err(format!(
+                "{:?} is not a log format, use json, pretty or compact.",
+                format
+            )),
+        }
+    }
+}
+
+impl FromStr for Resolver {
+    type Err = String;
+
+    fn from_str(resolver: &str) -> Result<Self, Self::Err> {
+        match resolver.to_lowercase().as_str() {
+            "system" => Ok(Resolver::System),
+            "cloudflare" => Ok(Resolver::Cloudflare),
+            "google" => Ok(Resolver::Google),
+            "quad9" => Ok(Resolver::Quad9),
+            _ => Err(format!(
+                "{:?} is not a resolver, use system, cloudflare, google or quad9.",
+                resolver
+            )),
+        }
+    }
+}
+
+impl Resolver {
+    /// The resolver for the driver, `None` for the one of the system.
+    pub fn resolver_config(self) -> Option<ResolverConfig> {
+        match self {
+            Resolver::System => None,
+            Resolver::Cloudflare => Some(ResolverConfig::cloudflare()),
+            Resolver::Google => Some(ResolverConfig::google()),
+            Resolver::Quad9 => Some(ResolverConfig::quad9()),
+        }
+    }
+}
+
+/// Why the config could not be read.
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Env(&'static str, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => write!(f, "Could not read {}. {}", path.display(), err),
+            ConfigError::Parse(path, err) => {
+                write!(f, "{} is not a valid config. {}", path.display(), err)
+            }
+            ConfigError::Env(name, err) => write!(f, "{} is invalid. {}", name, err),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the file at `CONFIG_PATH`, or `config.toml` if it exists, and applies the
+    /// environment on top. Values are only checked for their type here, see `validate`.
+    pub fn load() -> Result<Config, ConfigError> {
+        let (path, required) = match env_var("CONFIG_PATH") {
+            Some(path) => (PathBuf::from(path), true),
+            None => (PathBuf::from(DEFAULT_PATH), false),
+        };
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                toml::from_str(&contents).map_err(|err| ConfigError::Parse(path, err))?
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && !required => {
+                Config::default()
+            }
+            Err(err) => return Err(ConfigError::Read(path, err)),
+        };
+        config.apply_env(|name| env::var(name).ok())?;
+        Ok(config)
+    }
+
+    /// Overrides the settings with the environment variables `vars` looks up.
+    fn apply_env(&mut self, vars: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        let env = Overrides(vars);
+        env.string("DISCORD_TOKEN", &mut self.discord.token);
+        env.parsed("APPLICATION_ID", &mut self.discord.application_id)?;
+        if let Some(intents) = env.get("INTENTS") {
+            self.discord.intents = intents
+                .split(',')
+                .map(|intent| intent.trim().to_string())
+                .filter(|intent| !intent.is_empty())
+                .collect();
+        }
+
+        env.string("MONGO_CONN_STR", &mut self.mongo.uri);
+        env.string("DB_NAME", &mut self.mongo.db_name);
+        env.parsed("MONGO_RESOLVER", &mut self.mongo.resolver)?;
+
+        env.parsed("LOG_FORMAT", &mut self.log.format)?;
+        env.parsed("DEFAULT_VOLUME", &mut self.player.default_volume)?;
+        env.parsed(
+            "EMPTY_CHANNEL_GRACE_SECS",
+            &mut self.player.empty_channel_grace_secs,
+        )?;
+        env.parsed(
+            "FFMPEG_MAX_CONCURRENT",
+            &mut self.player.ffmpeg_max_concurrent,
+        )?;
+
+        let ytdl = &mut self.ytdl;
+        env.string("YTDL_PATH", &mut ytdl.path);
+        env.string("YTDL_FORMAT", &mut ytdl.format);
+        env.optional("YTDL_COOKIES", &mut ytdl.cookies);
+        env.optional("YTDL_PROXY", &mut ytdl.proxy);
+        if let Some(args) = env.get("YTDL_EXTRA_ARGS") {
+            ytdl.extra_args = args.split_whitespace().map(str::to_string).collect();
+        }
+        env.parsed("YTDL_MAX_CONCURRENT", &mut ytdl.max_concurrent)?;
+        env.parsed("YTDL_TIMEOUT_SECS", &mut ytdl.timeout_secs)?;
+        env.parsed(
+            "YTDL_PLAYLIST_TIMEOUT_SECS",
+            &mut ytdl.playlist_timeout_secs,
+        )?;
+
+        env.optional("AUDIO_CACHE_DIR", &mut self.cache.dir);
+        env.parsed("AUDIO_CACHE_MAX_MB", &mut self.cache.max_mb)?;
+        env.parsed("HISTORY_RETENTION_DAYS", &mut self.history.retention_days)?;
+        env.string("SPONSORBLOCK_API_URL", &mut self.sponsorblock.api_url);
+        env.optional("LYRICS_DIR", &mut self.lyrics.dir);
+        env.string("LYRICS_API_URL", &mut self.lyrics.api_url);
+
+        env.parsed("FEATURE_AUDIO_CACHE", &mut self.features.audio_cache)?;
+        env.parsed("FEATURE_SPONSORBLOCK", &mut self.features.sponsorblock)?;
+        env.parsed("FEATURE_ALWAYS_ON", &mut self.features.always_on)?;
+        Ok(())
+    }
+
+    /// The gateway intents the client connects with.
+    pub fn intents(&self) -> Result<GatewayIntents, String> {
+        let mut intents = GatewayIntents::empty();
+        for name in &self.discord.intents {
+            intents |=
+                parse_intent(name).ok_or_else(|| format!("{:?} is not a gateway intent.", name))?;
+        }
+        Ok(intents)
+    }
+
+    /// Checks the settings that have no sensible default or a limited range, describing the
+    /// first problem found.
+    pub fn validate(&self) -> Result<String, String> {
+        if self.mongo.db_name.trim().is_empty() {
+            return Err("The database name is empty.".to_string());
+        }
+        if !(0.0..=2.0).contains(&self.player.default_volume) {
+            return Err(format!(
+                "The default volume {} is not between 0 and 2.",
+                self.player.default_volume
+            ));
+        }
+        if self.player.empty_channel_grace_secs == 0 {
+            return Err("The empty channel grace period must be at least a second.".to_string());
+        }
+        if self.player.ffmpeg_max_concurrent == 0 {
+            return Err("ffmpeg needs at least one concurrent pipeline.".to_string());
+        }
+        if self.ytdl.max_concurrent == 0 {
+            return Err("yt-dlp needs at least one concurrent lookup.".to_string());
+        }
+        if self.ytdl.timeout_secs == 0 || self.ytdl.playlist_timeout_secs == 0 {
+            return Err("The yt-dlp timeouts must be at least a second.".to_string());
+        }
+        if self.cache.max_mb == 0 {
+            return Err("The audio cache needs room for at least a megabyte.".to_string());
+        }
+        if self.history.retention_days == 0 {
+            return Err("The history has to be kept for at least a day.".to_string());
+        }
+        check_url("The SponsorBlock API", &self.sponsorblock.api_url)?;
+        if !self.lyrics.api_url.is_empty() {
+            check_url("The lyrics API", &self.lyrics.api_url)?;
+        }
+        let intents = self.intents()?;
+        if !intents.contains(GatewayIntents::GUILDS | GatewayIntents::GUILD_VOICE_STATES) {
+            return Err("The intents need guilds and guild_voice_states for voice.".to_string());
+        }
+        Ok(format!(
+            "database {}, {:?} logs, volume {}",
+            self.mongo.db_name, self.log.format, self.player.default_volume
+        ))
+    }
+}
+
+fn parse_intent(name: &str) -> Option<GatewayIntents> {
+    let intent = match name.trim().to_lowercase().as_str() {
+        "all" => GatewayIntents::all(),
+        "non_privileged" => GatewayIntents::non_privileged(),
+        "guilds" => GatewayIntents::GUILDS,
+        "guild_members" => GatewayIntents::GUILD_MEMBERS,
+        "guild_bans" => GatewayIntents::GUILD_BANS,
+        "guild_emojis_and_stickers" => GatewayIntents::GUILD_EMOJIS_AND_STICKERS,
+        "guild_integrations" => GatewayIntents::GUILD_INTEGRATIONS,
+        "guild_webhooks" => GatewayIntents::GUILD_WEBHOOKS,
+        "guild_invites" => GatewayIntents::GUILD_INVITES,
+        "guild_voice_states" => GatewayIntents::GUILD_VOICE_STATES,
+        "guild_presences" => GatewayIntents::GUILD_PRESENCES,
+        "guild_messages" => GatewayIntents::GUILD_MESSAGES,
+        "guild_message_reactions" => GatewayIntents::GUILD_MESSAGE_REACTIONS,
+        "guild_message_typing" => GatewayIntents::GUILD_MESSAGE_TYPING,
+        "direct_messages" => GatewayIntents::DIRECT_MESSAGES,
+        "direct_message_reactions" => GatewayIntents::DIRECT_MESSAGE_REACTIONS,
+        "direct_message_typing" => GatewayIntents::DIRECT_MESSAGE_TYPING,
+        "message_content" => GatewayIntents::MESSAGE_CONTENT,
+        "guild_scheduled_events" => GatewayIntents::GUILD_SCHEDULED_EVENTS,
+        "auto_moderation_configuration" => GatewayIntents::AUTO_MODERATION_CONFIGURATION,
+        "auto_moderation_execution" => GatewayIntents::AUTO_MODERATION_EXECUTION,
+        _ => return None,
+    };
+    Some(intent)
+}
+
+fn check_url(what: &str, url: &str) -> Result<(), String> {
+    match Url::parse(url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Ok(()),
+        _ => Err(format!("{} URL {:?} is not an http(s) URL.", what, url)),
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+/// Looks up the variables that override the config. Blank values count as unset.
+struct Overrides<F>(F);
+
+impl<F: Fn(&str) -> Option<String>> Overrides<F> {
+    fn get(&self, name: &str) -> Option<String> {
+        (self.0)(name).filter(|value| !value.trim().is_empty())
+    }
+
+    fn string(&self, name: &str, value: &mut String) {
+        if let Some(var) = self.get(name) {
+            *value = var;
+        }
+    }
+
+    fn optional(&self, name: &str, value: &mut Option<String>) {
+        if let Some(var) = self.get(name) {
+            *value = Some(var);
+        }
+    }
+
+    fn parsed<T>(&self, name: &'static str, value: &mut T) -> Result<(), ConfigError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        if let Some(var) = self.get(name) {
+            *value = var
+                .trim()
+                .parse()
+                .map_err(|err: T::Err| ConfigError::Env(name, err.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+static CONFIG: OnceLock<Arc<Config>> = OnceLock::new();
+
+/// Makes the config the one `config()` returns. Only the first call has an effect.
+pub fn install(config: Config) -> Arc<Config> {
+    CONFIG.get_or_init(|| Arc::new(config)).clone()
+}
+
+/// The config of the bot, for code that has no `Context` at hand, like the songbird event
+/// handlers. Handlers with a `Context` read it from the data with `bot_config`.
+pub fn config() -> &'static Config {
+    CONFIG
+        .get()
+        .expect("The config is installed at the start of main.")
+}
+
+/// The config in the serenity data.
+pub struct BotConfig;
+
+impl TypeMapKey for BotConfig {
+    type Value = Arc<Config>;
+}
+
+/// The config of the bot, from the serenity data.
+pub async fn bot_config(ctx: &Context) -> Arc<Config> {
+    ctx.data
+        .read()
+        .await
+        .get::<BotConfig>()
+        .cloned()
+        .expect("The config is placed in at initialisation.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn with_env(vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let mut config = Config::default();
+        config.apply_env(|name| vars.get(name).cloned())?;
+        Ok(config)
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        let config: Config = toml::from_str(include_str!("../config.example.toml")).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn env_overrides_the_file() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [mongo]
+            db_name = "from_file"
+            [history]
+            retention_days = 30
+            "#,
+        )
+        .unwrap();
+        let vars: HashMap<&str, &str> = [
+            ("DB_NAME", "from_env"),
+            ("YTDL_EXTRA_ARGS", "--no-cache-dir  --geo-bypass"),
+            ("INTENTS", "guilds, guild_voice_states,"),
+            ("FEATURE_SPONSORBLOCK", "false"),
+            ("LYRICS_DIR", "/srv/lyrics"),
+        ]
+        .into_iter()
+        .collect();
+        config
+            .apply_env(|name| vars.get(name).map(|value| value.to_string()))
+            .unwrap();
+
+        assert_eq!(config.mongo.db_name, "from_env");
+        assert_eq!(config.history.retention_days, 30);
+        assert_eq!(config.ytdl.extra_args, ["--no-cache-dir", "--geo-bypass"]);
+        assert_eq!(config.discord.intents, ["guilds", "guild_voice_states"]);
+        assert!(!config.features.sponsorblock);
+        assert_eq!(config.lyrics.dir.as_deref(), Some("/srv/lyrics"));
+    }
+
+    #[test]
+    fn retention_is_in_days() {
+        let config: Config = toml::from_str("[history]\nretention_days = 30").unwrap();
+        assert_eq!(
+            config.history.retention(),
+            Duration::from_secs(30 * 24 * 60 * 60)
+        );
+        let config = with_env(&[("HISTORY_RETENTION_DAYS", "7")]).unwrap();
+        assert_eq!(
+            config.history.retention(),
+            Duration::from_secs(7 * 24 * 60 * 60)
+        );
+        assert_eq!(
+            Config::default().history.retention(),
+            Duration::from_secs(90 * 24 * 60 * 60)
+        );
+    }
+
+    #[test]
+    fn blank_env_is_ignored() {
+        let config = with_env(&[("DB_NAME", "  "), ("AUDIO_CACHE_DIR", "")]).unwrap();
+        assert_eq!(config.mongo.db_name, "botdb");
+        assert_eq!(config.cache.dir, None);
+    }
+
+    #[test]
+    fn unparsable_env_names_the_variable() {
+        match with_env(&[("HISTORY_RETENTION_DAYS", "a week")]) {
+            Err(ConfigError::Env(name, _)) => assert_eq!(name, "HISTORY_RETENTION_DAYS"),
+            other => panic!("expected an env error, got {:?}", other.map(|_| ())),
+        }
+        assert!(with_env(&[("LOG_FORMAT", "xml")]).is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("[player]\nvolume = 1.0").is_err());
+    }
+
+    #[test]
+    fn validation_rejects_out_of_range_values() {
+        let invalid = [
+            ("DEFAULT_VOLUME", "2.5"),
+            ("EMPTY_CHANNEL_GRACE_SECS", "0"),
+            ("FFMPEG_MAX_CONCURRENT", "0"),
+            ("AUDIO_CACHE_MAX_MB", "0"),
+            ("HISTORY_RETENTION_DAYS", "0"),
+            ("YTDL_MAX_CONCURRENT", "0"),
+            ("SPONSORBLOCK_API_URL", "sponsor.ajay.app"),
+            ("LYRICS_API_URL", "ftp://lrclib.net"),
+            ("INTENTS", "guilds"),
+            ("INTENTS", "guilds,voice"),
+        ];
+        for (name, value) in invalid {
+            let config = with_env(&[(name, value)]).unwrap();
+            assert!(config.validate().is_err(), "{}={} passed", name, value);
+        }
+    }
+
+    #[test]
+    fn empty_lyrics_url_turns_the_api_off() {
+        let config: Config = toml::from_str("[lyrics]\napi_url = \"\"").unwrap();
+        assert!(config.validate().is_ok());
+    }
+}
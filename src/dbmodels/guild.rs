@@ -7,11 +7,49 @@ pub struct Guild {
     pub mod_channel_ID: String,
     pub mod_role_ID: String,
     pub prefix_string: String,
-    pub volume: f32
+    pub volume: f32,
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout: u64,
+    #[serde(default)]
+    pub always_on: bool,
+    #[serde(default = "default_channel_id")]
+    pub always_on_channel_ID: String,
+    #[serde(default)]
+    pub saved_queue: Vec<String>,
+    #[serde(default = "default_channel_id")]
+    pub announce_channel_ID: String,
+    #[serde(default)]
+    pub announce_mode: AnnounceMode,
+    #[serde(default)]
+    pub announce_tidy: bool,
+    /// SponsorBlock categories to skip, none turns skipping off.
+    #[serde(default)]
+    pub sponsorblock_categories: Vec<String>,
+    #[serde(default)]
+    pub audio_cache: bool,
+}
+
+/// How track starts are announced in the announce channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnounceMode {
+    #[default]
+    Off,
+    Embed,
+    Compact,
+}
+
+pub fn default_channel_id() -> String {
+    "0".to_string()
+}
+
+/// Minutes without playback before the bot leaves, 0 disables it.
+pub fn default_idle_timeout() -> u64 {
+    10
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-#[allow(non_snake_case)]
+#[allow(non_snake_case, dead_code)]
 pub struct SocialMediaAccounts {
     pub account_type: String,
     pub account_ID: String,
@@ -0,0 +1,19 @@
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::*;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct HistoryEntry {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub guild_ID: String,
+    pub channel_ID: String,
+    pub url: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub requester_ID: String,
+    pub started_at: DateTime,
+    pub ended_at: Option<DateTime>,
+    pub skipped: bool,
+}
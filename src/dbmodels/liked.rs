@@ -0,0 +1,18 @@
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::*;
+
+/// A track a user liked, with the metadata as it was when they liked it.
+#[derive(Debug, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct LikedTrack {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_ID: String,
+    pub url: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub duration_secs: Option<u64>,
+    pub thumbnail: Option<String>,
+    pub liked_at: DateTime,
+}
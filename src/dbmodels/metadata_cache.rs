@@ -0,0 +1,12 @@
+use mongodb::bson::DateTime;
+use serde::*;
+
+/// yt-dlp output for a URL, search query or playlist, so it doesn't have to run again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedMetadata {
+    /// The normalized URL or query, prefixed with what kind of lookup it was.
+    pub key: String,
+    /// The JSON yt-dlp printed, trimmed down to the fields the bot reads.
+    pub json: String,
+    pub fetched_at: DateTime,
+}
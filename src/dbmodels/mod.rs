@@ -1 +1,4 @@
 pub mod guild;
+pub mod history;
+pub mod liked;
+pub mod metadata_cache;
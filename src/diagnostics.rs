@@ -0,0 +1,240 @@
+use audiopus::coder::Encoder;
+use audiopus::{Application, Channels, SampleRate};
+use serde::Serialize;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command as TokioCommand;
+use tracing::{error, info};
+
+use crate::config::Config;
+use crate::mongo_conn::Db;
+use crate::player::ytdl::extractor;
+
+/// Exit code for missing or invalid environment variables (`EX_CONFIG`).
+pub const EXIT_CONFIG: i32 = 78;
+
+/// Exit code for a missing or broken yt-dlp, ffmpeg or libopus (`EX_UNAVAILABLE`).
+pub const EXIT_UNAVAILABLE: i32 = 69;
+
+/// Exit code for when MongoDB can't be reached (`EX_TEMPFAIL`).
+pub const EXIT_DATABASE: i32 = 75;
+
+/// How long a binary or the database gets to answer.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    /// Works, but a feature will be missing.
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// What the process exits with when this check fails.
+    #[serde(skip)]
+    exit_code: i32,
+}
+
+impl Check {
+    fn new(name: &'static str, exit_code: i32, res: Result<String, String>) -> Check {
+        let (status, detail) = match res {
+            Ok(detail) => (Status::Ok, detail),
+            Err(detail) => (Status::Fail, detail),
+        };
+        Check {
+            name,
+            status,
+            detail,
+            exit_code,
+        }
+    }
+}
+
+/// The outcome of every startup check.
+#[derive(Serialize)]
+pub struct Report {
+    pub ok: bool,
+    pub checks: Vec<Check>,
+    /// The database, connected while checking it so the bot can keep using it.
+    #[serde(skip)]
+    pub db: Option<Db>,
+}
+
+impl Report {
+    /// The exit code of the first failed check, if any failed.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.checks
+            .iter()
+            .find(|check| check.status == Status::Fail)
+            .map(|check| check.exit_code)
+    }
+
+    /// Logs the whole report as one event.
+    pub fn log(&self) {
+        let report = serde_json::to_string(self).unwrap_or_default();
+        if self.ok {
+            info!(report = %report, "Startup checks passed.");
+        } else {
+            error!(report = %report, "Startup checks failed.");
+        }
+    }
+}
+
+/// Checks the config, the external binaries and the database. The database is only tried when
+/// the config is valid.
+pub async fn run(config: &Config) -> Report {
+    let mut checks = vec![
+        Check::new("DISCORD_TOKEN", EXIT_CONFIG, check_token(config)),
+        Check::new("APPLICATION_ID", EXIT_CONFIG, check_application_id(config)),
+        Check::new("MONGO_CONN_STR", EXIT_CONFIG, check_mongo_uri(config)),
+        Check::new("config", EXIT_CONFIG, config.validate()),
+    ];
+    let config_ok = checks.iter().all(|check| check.status == Status::Ok);
+
+    checks.push(Check::new(
+        "yt-dlp",
+        EXIT_UNAVAILABLE,
+        version(&extractor().config.path, "--version").await,
+    ));
+    checks.push(Check::new(
+        "ffmpeg",
+        EXIT_UNAVAILABLE,
+        version("ffmpeg", "-version").await,
+    ));
+    checks.push(check_ffmpeg_libopus().await);
+    checks.push(Check::new("libopus", EXIT_UNAVAILABLE, check_libopus()));
+
+    let mut db = None;
+    if config_ok {
+        let res = match connect(config).await {
+            Ok(connected) => {
+                db = Some(connected);
+                Ok("reachable".to_string())
+            }
+            Err(err) => Err(err),
+        };
+        checks.push(Check::new("MongoDB", EXIT_DATABASE, res));
+    }
+
+    Report {
+        ok: checks.iter().all(|check| check.status != Status::Fail),
+        checks,
+        db,
+    }
+}
+
+fn check_token(config: &Config) -> Result<String, String> {
+    let token = &config.discord.token;
+    if token.trim().is_empty() {
+        return Err("DISCORD_TOKEN is not set.".to_string());
+    }
+    if token.split('.').count() != 3 {
+        return Err("DISCORD_TOKEN does not look like a bot token.".to_string());
+    }
+    Ok("set".to_string())
+}
+
+fn check_application_id(config: &Config) -> Result<String, String> {
+    match config.discord.application_id {
+        0 => Err("APPLICATION_ID is not set.".to_string()),
+        application_id => Ok(application_id.to_string()),
+    }
+}
+
+fn check_mongo_uri(config: &Config) -> Result<String, String> {
+    let conn_str = &config.mongo.uri;
+    if conn_str.trim().is_empty() {
+        return Err("MONGO_CONN_STR is not set.".to_string());
+    }
+    if !conn_str.starts_with("mongodb://") && !conn_str.starts_with("mongodb+srv://") {
+        return Err("MONGO_CONN_STR must start with mongodb:// or mongodb+srv://.".to_string());
+    }
+    Ok("set".to_string())
+}
+
+/// Runs the binary with its version flag, returning the first line it printed.
+async fn version(binary: &str, flag: &str) -> Result<String, String> {
+    let output = run_binary(binary, &[flag]).await?;
+    Ok(String::from_utf8_lossy(&output)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
+
+async fn run_binary(binary: &str, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = TokioCommand::new(binary)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    match tokio::time::timeout(CHECK_TIMEOUT, output).await {
+        Ok(Ok(output)) if output.status.success() => Ok(output.stdout),
+        Ok(Ok(output)) => Err(format!("{} exited with {}.", binary, output.status)),
+        Ok(Err(err)) => Err(format!("Could not run {}. {}", binary, err)),
+        Err(_) => Err(format!(
+            "{} did not answer within {}s.",
+            binary,
+            CHECK_TIMEOUT.as_secs()
+        )),
+    }
+}
+
+/// The audio cache transcodes with ffmpeg's libopus encoder, playback works without it.
+async fn check_ffmpeg_libopus() -> Check {
+    let mut check = Check::new(
+        "ffmpeg libopus",
+        EXIT_UNAVAILABLE,
+        run_binary("ffmpeg", &["-hide_banner", "-encoders"])
+            .await
+            .map(|encoders| {
+                if String::from_utf8_lossy(&encoders).contains("libopus") {
+                    "available".to_string()
+                } else {
+                    String::new()
+                }
+            }),
+    );
+    // A missing ffmpeg already fails its own check.
+    if check.status == Status::Fail {
+        check.status = Status::Warn;
+    } else if check.detail.is_empty() {
+        check.status = Status::Warn;
+        check.detail = "ffmpeg has no libopus encoder, the audio cache won't work.".to_string();
+    }
+    check
+}
+
+/// Creates an Opus encoder, which is what the voice driver needs libopus for.
+fn check_libopus() -> Result<String, String> {
+    match Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio) {
+        Ok(_) => Ok("encoder created".to_string()),
+        Err(err) => Err(format!("Could not create an Opus encoder. {}", err)),
+    }
+}
+
+/// Connects to the database and pings it.
+async fn connect(config: &Config) -> Result<Db, String> {
+    let ping = async {
+        let db = Db::connect(&config.mongo).await?;
+        db.ping().await?;
+        Ok::<_, mongodb::error::Error>(db)
+    };
+    match tokio::time::timeout(CHECK_TIMEOUT, ping).await {
+        Ok(Ok(db)) => Ok(db),
+        Ok(Err(err)) => Err(format!("Could not reach MongoDB. {}", err)),
+        Err(_) => Err(format!(
+            "MongoDB did not answer within {}s.",
+            CHECK_TIMEOUT.as_secs()
+        )),
+    }
+}
@@ -0,0 +1,72 @@
+use serde::Deserialize;
+use serenity::async_trait;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::lyrics::{Lyrics, LyricsProvider, Query};
+
+/// Looks lyrics up through an LRCLIB-compatible search API.
+pub struct HttpProvider {
+    base_url: String,
+}
+
+impl HttpProvider {
+    pub fn new(base_url: impl Into<String>) -> HttpProvider {
+        HttpProvider {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResult {
+    plain_lyrics: Option<String>,
+    synced_lyrics: Option<String>,
+}
+
+#[async_trait]
+impl LyricsProvider for HttpProvider {
+    fn name(&self) -> &'static str {
+        "the lyrics API"
+    }
+
+    async fn lyrics(&self, query: &Query) -> Option<Lyrics> {
+        let mut params = vec![("track_name", query.title.as_str())];
+        if let Some(artist) = &query.artist {
+            params.push(("artist_name", artist.as_str()));
+        }
+
+        let response = reqwest::Client::new()
+            .get(format!("{}/api/search", self.base_url))
+            .query(&params)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        let results: Vec<SearchResult> = match response {
+            Ok(response) => match response.json().await {
+                Ok(results) => results,
+                Err(err) => {
+                    warn!("Could not read the lyrics search results. {}", err);
+                    return None;
+                }
+            },
+            Err(err) => {
+                warn!("Could not search for lyrics. {}", err);
+                return None;
+            }
+        };
+
+        let result = results
+            .iter()
+            .find(|result| result.synced_lyrics.is_some())
+            .or_else(|| results.first())?;
+        result
+            .synced_lyrics
+            .as_deref()
+            .or(result.plain_lyrics.as_deref())
+            .filter(|text| !text.trim().is_empty())
+            .map(Lyrics::parse)
+    }
+}
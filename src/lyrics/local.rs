@@ -0,0 +1,72 @@
+use serenity::async_trait;
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::lyrics::{Lyrics, LyricsProvider, Query};
+
+/// Reads lyrics from a directory of `Artist - Title.lrc` or `Title.txt` files. File names are
+/// matched without regard to case, `.lrc` files are preferred.
+pub struct LocalProvider {
+    dir: PathBuf,
+}
+
+impl LocalProvider {
+    pub fn new(dir: impl Into<PathBuf>) -> LocalProvider {
+        LocalProvider { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl LyricsProvider for LocalProvider {
+    fn name(&self) -> &'static str {
+        "local files"
+    }
+
+    async fn lyrics(&self, query: &Query) -> Option<Lyrics> {
+        let mut stems = vec![];
+        if let Some(artist) = &query.artist {
+            stems.push(format!("{} - {}", artist, query.title).to_lowercase());
+        }
+        stems.push(query.title.to_lowercase());
+
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!(
+                    "Could not read the lyrics directory {:?}. {}",
+                    self.dir, err
+                );
+                return None;
+            }
+        };
+        let mut best: Option<(usize, PathBuf)> = None;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            let extension = path.extension().and_then(|ext| ext.to_str());
+            let extension_rank = match extension.map(str::to_lowercase).as_deref() {
+                Some("lrc") => 0,
+                Some("txt") => 1,
+                _ => continue,
+            };
+            let stem = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(stem) => stem.to_lowercase(),
+                None => continue,
+            };
+            if let Some(stem_rank) = stems.iter().position(|wanted| *wanted == stem) {
+                let rank = stem_rank * 2 + extension_rank;
+                if best.as_ref().is_none_or(|(best_rank, _)| rank < *best_rank) {
+                    best = Some((rank, path));
+                }
+            }
+        }
+
+        let (_, path) = best?;
+        match tokio::fs::read_to_string(&path).await {
+            Ok(text) => Some(Lyrics::parse(&text)),
+            Err(err) => {
+                warn!("Could not read the lyrics file {:?}. {}", path, err);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,299 @@
+pub mod http;
+pub mod local;
+
+use serenity::async_trait;
+use serenity::prelude::TypeMapKey;
+use songbird::input::Metadata;
+use std::time::Duration;
+use tracing::info;
+
+use crate::config::config;
+use crate::lyrics::http::HttpProvider;
+use crate::lyrics::local::LocalProvider;
+
+/// A line of synced lyrics.
+#[derive(Debug, Clone)]
+pub struct LyricLine {
+    pub time: Duration,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Lyrics {
+    Plain(String),
+    /// Lines with their start times, sorted by time.
+    Synced(Vec<LyricLine>),
+}
+
+impl Lyrics {
+    /// Reads lyrics in the LRC format, falling back to plain text when there are no time tags.
+    pub fn parse(text: &str) -> Lyrics {
+        let mut offset_ms: i64 = 0;
+        let mut lines = vec![];
+        for line in text.lines() {
+            let mut rest = line.trim();
+            let mut times = vec![];
+            while let Some(tag) = rest.strip_prefix('[') {
+                let (tag, after) = match tag.split_once(']') {
+                    Some(split) => split,
+                    None => break,
+                };
+                if let Some(offset) = tag.strip_prefix("offset:") {
+                    offset_ms = offset.trim().parse().unwrap_or(0);
+                } else if let Some(time) = parse_time_tag(tag) {
+                    times.push(time);
+                }
+                rest = after;
+            }
+            for time in times {
+                lines.push((time, rest.trim().to_string()));
+            }
+        }
+
+        if lines.is_empty() {
+            return Lyrics::Plain(text.trim().to_string());
+        }
+
+        // A positive offset shows the lyrics earlier.
+        let mut lines: Vec<LyricLine> = lines
+            .into_iter()
+            .map(|(time, text)| LyricLine {
+                time: Duration::from_millis((time - offset_ms).max(0) as u64),
+                text,
+            })
+            .collect();
+        lines.sort_by_key(|line| line.time);
+        Lyrics::Synced(lines)
+    }
+
+    /// The lyrics without any timing.
+    pub fn text(&self) -> String {
+        match self {
+            Lyrics::Plain(text) => text.clone(),
+            Lyrics::Synced(lines) => lines
+                .iter()
+                .map(|line| line.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// Reads an `mm:ss.xx` time tag into milliseconds.
+fn parse_time_tag(tag: &str) -> Option<i64> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: i64 = minutes.trim().parse().ok()?;
+    let seconds: f64 = seconds.trim().parse().ok()?;
+    Some(minutes * 60_000 + (seconds * 1000.0).round() as i64)
+}
+
+/// Lyrics along with where they were found.
+#[derive(Debug, Clone)]
+pub struct FoundLyrics {
+    pub lyrics: Lyrics,
+    pub source: &'static str,
+}
+
+/// The lyrics of the track, stored in the track's typemap once they were looked up. None when
+/// no provider had any.
+pub struct TrackLyrics;
+
+impl TypeMapKey for TrackLyrics {
+    type Value = Option<FoundLyrics>;
+}
+
+/// The artist and title to look a track up by.
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub artist: Option<String>,
+    pub title: String,
+}
+
+impl Query {
+    /// Uses the track and artist fields when the source has them, otherwise splits video titles
+    /// like "Artist - Title (Official Video)".
+    pub fn from_metadata(metadata: &Metadata) -> Option<Query> {
+        if let Some(track) = &metadata.track {
+            return Some(Query {
+                artist: metadata.artist.clone(),
+                title: track.clone(),
+            });
+        }
+
+        let title = strip_brackets(metadata.title.as_deref()?);
+        match title.split_once(" - ") {
+            Some((artist, title)) => Some(Query {
+                artist: Some(artist.trim().to_string()),
+                title: title.trim().to_string(),
+            }),
+            None => Some(Query {
+                artist: metadata.artist.clone(),
+                title: title.trim().to_string(),
+            }),
+        }
+    }
+}
+
+/// Removes the "(Official Video)" and "[Lyrics]" parts of a title.
+fn strip_brackets(title: &str) -> String {
+    let mut stripped = String::new();
+    let mut depth = 0;
+    for c in title.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = (depth - 1).max(0),
+            _ if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A place lyrics can be looked up in.
+#[async_trait]
+pub trait LyricsProvider: Send + Sync {
+    /// Shown under the lyrics.
+    fn name(&self) -> &'static str;
+
+    async fn lyrics(&self, query: &Query) -> Option<Lyrics>;
+}
+
+/// The configured providers, in the order they are asked. `lyrics.dir` enables the local
+/// directory, `lyrics.api_url` points the HTTP provider at an LRCLIB-compatible API.
+pub fn providers() -> Vec<Box<dyn LyricsProvider>> {
+    let lyrics = &config().lyrics;
+    let mut providers: Vec<Box<dyn LyricsProvider>> = vec![];
+    if let Some(dir) = &lyrics.dir {
+        providers.push(Box::new(LocalProvider::new(dir.clone())));
+    }
+    if !lyrics.api_url.is_empty() {
+        providers.push(Box::new(HttpProvider::new(lyrics.api_url.clone())));
+    }
+    providers
+}
+
+/// Asks every provider for the lyrics of the track. Synced lyrics win over plain ones.
+pub async fn find(metadata: &Metadata) -> Option<FoundLyrics> {
+    let query = Query::from_metadata(metadata)?;
+    let mut plain = None;
+    for provider in providers() {
+        match provider.lyrics(&query).await {
+            Some(lyrics @ Lyrics::Synced(_)) => {
+                info!(
+                    "Found synced lyrics for {:?} in {}.",
+                    query,
+                    provider.name()
+                );
+                return Some(FoundLyrics {
+                    lyrics,
+                    source: provider.name(),
+                });
+            }
+            Some(lyrics) if plain.is_none() => {
+                plain = Some(FoundLyrics {
+                    lyrics,
+                    source: provider.name(),
+                })
+            }
+            _ => {}
+        }
+    }
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synced(lyrics: Lyrics) -> Vec<(u64, String)> {
+        match lyrics {
+            Lyrics::Synced(lines) => lines
+                .into_iter()
+                .map(|line| (line.time.as_millis() as u64, line.text))
+                .collect(),
+            Lyrics::Plain(text) => panic!("expected synced lyrics, got {:?}", text),
+        }
+    }
+
+    #[test]
+    fn lrc_lines_are_sorted_by_time() {
+        let lyrics =
+            Lyrics::parse("[ar:Someone]\n[00:12.50]Second\n[00:01.00]First\n[01:02.345]Third\n");
+        assert_eq!(
+            synced(lyrics),
+            [
+                (1_000, "First".to_string()),
+                (12_500, "Second".to_string()),
+                (62_345, "Third".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_lines_get_every_time_tag() {
+        let lyrics = Lyrics::parse("[00:05.00][00:20.00] Chorus \n[00:10.00]Verse");
+        assert_eq!(
+            synced(lyrics),
+            [
+                (5_000, "Chorus".to_string()),
+                (10_000, "Verse".to_string()),
+                (20_000, "Chorus".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn offset_moves_lines_earlier() {
+        let lyrics = Lyrics::parse("[offset:+500]\n[00:00.20]Start\n[00:02.00]Next");
+        assert_eq!(
+            synced(lyrics),
+            [(0, "Start".to_string()), (1_500, "Next".to_string())]
+        );
+    }
+
+    #[test]
+    fn text_without_time_tags_is_plain() {
+        match Lyrics::parse("  Just some words\nand more\n") {
+            Lyrics::Plain(text) => assert_eq!(text, "Just some words\nand more"),
+            Lyrics::Synced(_) => panic!("expected plain lyrics"),
+        }
+    }
+
+    fn metadata(title: &str, artist: Option<&str>, track: Option<&str>) -> Metadata {
+        Metadata {
+            title: Some(title.to_string()),
+            artist: artist.map(str::to_string),
+            track: track.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn query_prefers_the_track_field() {
+        let query =
+            Query::from_metadata(&metadata("Video title", Some("Artist"), Some("Song"))).unwrap();
+        assert_eq!(query.artist.as_deref(), Some("Artist"));
+        assert_eq!(query.title, "Song");
+    }
+
+    #[test]
+    fn query_splits_video_titles() {
+        let query = Query::from_metadata(&metadata(
+            "Artist - Song (Official Video) [HD]",
+            Some("Uploader"),
+            None,
+        ))
+        .unwrap();
+        assert_eq!(query.artist.as_deref(), Some("Artist"));
+        assert_eq!(query.title, "Song");
+    }
+
+    #[test]
+    fn query_falls_back_to_the_uploader() {
+        let query =
+            Query::from_metadata(&metadata("Song (Lyrics)", Some("Uploader"), None)).unwrap();
+        assert_eq!(query.artist.as_deref(), Some("Uploader"));
+        assert_eq!(query.title, "Song");
+        assert!(Query::from_metadata(&Metadata::default()).is_none());
+    }
+}
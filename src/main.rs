@@ -1,28 +1,45 @@
 mod application_commands;
 mod commands;
+mod config;
 mod dbmodels;
+mod diagnostics;
+mod lyrics;
 mod mongo_conn;
+mod player;
 mod startup;
 
 use mongodb::Collection;
 use serenity::model::application::interaction::Interaction;
 use songbird::SerenityInit;
 
-use std::{env, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    sync::Arc,
+};
 
-use crate::dbmodels::guild::Guild as GuildStruct;
-use mongo_conn::{get_collection, get_db, get_mongo_client};
+use crate::config::{bot_config, BotConfig, Config, LogFormat};
+use crate::dbmodels::guild::{
+    default_channel_id, default_idle_timeout, AnnounceMode, Guild as GuildStruct,
+};
+use mongo_conn::{db, BotDb};
 use serenity::{
     async_trait, framework::StandardFramework, model::prelude::GuildId, model::prelude::*,
     prelude::*,
 };
 use tracing::{debug, error, info, warn};
 
-use crate::startup::insert_guilds;
+use crate::player::always_on;
+use crate::player::empty_channel::{self, EmptyChannelPauses};
+use crate::player::guild_state::GuildPlayerStates;
+use crate::player::idle::{self, IdleWatchers};
+use crate::player::previous::PreviousTracks;
+use crate::player::reconnect;
+use crate::startup::{
+    create_history_indexes, create_liked_indexes, create_metadata_cache_indexes, insert_guilds,
+};
 
-struct Handler {
-    mongodb_client: mongodb::Client,
-}
+struct Handler;
 
 #[async_trait]
 impl EventHandler for Handler {
@@ -32,16 +49,6 @@ impl EventHandler for Handler {
         info!("Cache is ready, starting the redis-check-loop");
         let _ctx = Arc::new(ctx);
 
-        let mongo_conn_str = env::var("MONGO_CONN_STR").expect("Need a MongoDB connection string.");
-        let mongodb_client = match get_mongo_client(mongo_conn_str.as_str()).await {
-            Ok(client) => client,
-            Err(err) => {
-                panic!("Could not get mongoDB client. {:?}", err)
-            }
-        };
-
-        let _mongo_client = Arc::new(mongodb_client);
-
         // This is for if you want to run something else in a seperate thread
         // if !self.is_loop_running.load(Ordering::Relaxed) {
         //     info!("Starting the redis check loop");
@@ -75,35 +82,40 @@ impl EventHandler for Handler {
         //     application_commands::clear(&ctx).await;
         // }
 
-        let mongo_conn_str = env::var("MONGO_CONN_STR").expect("Need a MongoDB connection string.");
-        let client = match get_mongo_client(mongo_conn_str.as_str()).await {
-            Ok(client) => client,
-            Err(_) => {
-                panic!("Could not get mongoDB client.")
-            }
-        };
-        if let Err(err) = insert_guilds(&ctx, &client).await {
+        let db = db(&ctx).await;
+        let bot_config = bot_config(&ctx).await;
+        if let Err(err) = insert_guilds(&ctx, &db).await {
+            warn!("{:?}", err)
+        }
+        if let Err(err) = create_history_indexes(&db, bot_config.history.retention()).await {
+            warn!("{:?}", err)
+        }
+        if let Err(err) = create_liked_indexes(&db).await {
+            warn!("{:?}", err)
+        }
+        if let Err(err) = create_metadata_cache_indexes(&db).await {
             warn!("{:?}", err)
         }
 
         application_commands::register(&ctx).await;
+
+        if bot_config.features.always_on {
+            let rejoin_ctx = ctx.clone();
+            tokio::spawn(async move {
+                always_on::rejoin_all(&rejoin_ctx, &db).await;
+            });
+        }
     }
 
     // Interaction handler
     async fn interaction_create(&self, _ctx: Context, _interaction: Interaction) {
-        application_commands::handle_interactions(&_ctx, _interaction, &self.mongodb_client).await
+        let db = db(&_ctx).await;
+        application_commands::handle_interactions(&_ctx, _interaction, &db).await
     }
 
     async fn guild_create(&self, _ctx: Context, guild: Guild, _new: bool) {
-        let mongo_conn_str = env::var("MONGO_CONN_STR").expect("Need a MongoDB connection string.");
-        let client = match get_mongo_client(mongo_conn_str.as_str()).await {
-            Ok(client) => client,
-            Err(_) => {
-                panic!("Could not get mongoDB client.")
-            }
-        };
-        let db = get_db(&client, "botdb").await;
-        let col: Collection<GuildStruct> = get_collection(&db, "guilds", None).await;
+        let col: Collection<GuildStruct> = db(&_ctx).await.guilds();
+        let default_volume = bot_config(&_ctx).await.player.default_volume;
 
         let guild_id_str = guild.id.0.to_string();
 
@@ -114,7 +126,16 @@ impl EventHandler for Handler {
                     mod_channel_ID: "0".to_string(),
                     mod_role_ID: "0".to_string(),
                     prefix_string: "~".to_string(),
-                    volume: 0.7,
+                    volume: default_volume,
+                    idle_timeout: default_idle_timeout(),
+                    always_on: false,
+                    always_on_channel_ID: default_channel_id(),
+                    saved_queue: vec![],
+                    announce_channel_ID: default_channel_id(),
+                    announce_mode: AnnounceMode::Off,
+                    announce_tidy: false,
+                    sponsorblock_categories: vec![],
+                    audio_cache: false,
                 },
                 None,
             )
@@ -124,104 +145,101 @@ impl EventHandler for Handler {
     async fn voice_state_update(&self, ctx: Context, old_state_opt: Option<VoiceState>, new_state: VoiceState) {
 
         debug!("{:?}", new_state);
-
-        let old_state = match old_state_opt {
-            Some(state) => {
-                info!("{:?}", state);
-                info!("-->>");
-                info!("{:?}", new_state);
-                state
-            },
-            None => {
-                info!("{:?}", new_state);
-                return
-            }, // They are joining, don't care
-        };
-        
-        
-        
-        if new_state.channel_id.is_some() {
-            return
+        let db = db(&ctx).await;
+
+        if new_state.user_id == ctx.cache.current_user_id() {
+            match (new_state.guild_id, new_state.channel_id) {
+                // The bot joined a channel, keep an eye on it so it doesn't sit there idle forever.
+                (Some(guild_id), Some(channel_id)) => {
+                    let old_channel = old_state_opt.and_then(|state| state.channel_id);
+                    if old_channel.is_some() && old_channel != Some(channel_id) {
+                        reconnect::moved(guild_id, channel_id, &db).await;
+                    }
+                    idle::watch(&ctx, guild_id, &db).await;
+                }
+                (Some(guild_id), None) => {
+                    reconnect::dropped(&ctx, guild_id, &db).await;
+                }
+                _ => {}
+            }
+            return;
         }
 
-        let guild_channel: GuildChannel = match old_state.channel_id {
-            Some(id) => {
-                match id.to_channel(&ctx).await {
-                    Ok(channel) => {
-                        match channel.guild() {
-                            Some(guild_channel) => guild_channel,
-                            None => {
-                                warn!("No guild for channel with new voice state");
-                                return
-                            },
-                        } 
-                    },
-                    Err(_) => {
-                        error!("Failed to get guild_channel from channel");
-                        return
-                    },
-                }
-            },
+        let guild_id = match new_state.guild_id {
+            Some(guild_id) => guild_id,
             None => {
-                warn!("No channel id with new voice state");
-                return
-            },
-        };
-
-        let member_count = match guild_channel.members(&ctx).await {
-            Ok(memebers) => memebers.len(),
-            Err(_) => {
-                error!("Failed to get memebers from guild_channel");
-                return
-            },
+                warn!("No guild id with new voice state");
+                return;
+            }
         };
 
-        
-        if member_count <= 1 {
-            let manager = songbird::get(&ctx)
-            .await
-            .expect("Songbird Voice client placed in at initialisation.")
-            .clone();
-            
-            let _ = manager.leave(guild_channel.guild_id).await;
-        }
+        empty_channel::update(
+            &ctx,
+            guild_id,
+            old_state_opt.and_then(|state| state.channel_id),
+            new_state.channel_id,
+            &db,
+        )
+        .await;
     }
 }
 
 #[tokio::main]
 async fn main() {
+    // Nothing is logged before the config is read, it picks the log format.
+    let bot_config = match Config::load() {
+        Ok(bot_config) => config::install(bot_config),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(diagnostics::EXIT_CONFIG);
+        }
+    };
+
     // Initialize the tracing subscriber
-    tracing_subscriber::fmt().json().init();
+    match bot_config.log.format {
+        LogFormat::Json => tracing_subscriber::fmt().json().init(),
+        LogFormat::Pretty => tracing_subscriber::fmt().pretty().init(),
+        LogFormat::Compact => tracing_subscriber::fmt().compact().init(),
+    }
     info!("Starting the bot...");
 
-    let framework = StandardFramework::new().configure(|c| c.prefix("~")); // set the bot's prefix to "~"
+    // `--check` only runs the startup checks, for deployments and health checks.
+    let check_only = env::args().skip(1).any(|arg| arg == "--check");
+    let report = diagnostics::run(&bot_config).await;
+    report.log();
+    match report.exit_code() {
+        Some(code) => std::process::exit(code),
+        None if check_only => return,
+        None => {}
+    }
 
-    // Login with a bot token from the environment
-    let token = env::var("DISCORD_TOKEN").expect("Missing token in env!");
+    let framework = StandardFramework::new().configure(|c| c.prefix("~")); // set the bot's prefix to "~"
 
-    let application_id: u64 = env::var("APPLICATION_ID")
-        .expect("Expected an application id in the environment")
-        .parse()
-        .expect("application id is not a valid id");
+    let db = report
+        .db
+        .expect("The database is connected when the startup checks pass.");
 
-    let mongo_conn_str = env::var("MONGO_CONN_STR").expect("Need a MongoDB connection string.");
-    let mongodb_client = match get_mongo_client(mongo_conn_str.as_str()).await {
-        Ok(client) => client,
-        Err(err) => {
-            panic!("Could not get mongoDB client. {:?}", err)
-        }
-    };
-
-    let handler = Handler { mongodb_client };
-    let intents = GatewayIntents::all();
-    let mut client = Client::builder(token, intents)
+    let handler = Handler;
+    // Checked by the startup checks already.
+    let intents = bot_config.intents().expect("The intents are valid.");
+    let mut client = Client::builder(&bot_config.discord.token, intents)
         .event_handler(handler)
         .framework(framework)
         .register_songbird()
-        .application_id(application_id)
+        .application_id(bot_config.discord.application_id)
         .await
         .expect("Error creating client");
 
+    {
+        let mut data = client.data.write().await;
+        data.insert::<BotConfig>(bot_config.clone());
+        data.insert::<BotDb>(db);
+        data.insert::<IdleWatchers>(Arc::new(Mutex::new(HashSet::new())));
+        data.insert::<EmptyChannelPauses>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<PreviousTracks>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<GuildPlayerStates>(Arc::new(Mutex::new(HashMap::new())));
+    }
+
     // start listening for events by starting a single shard
     if let Err(why) = client.start().await {
         error!("An error occurred while running the client: {:?}", why);
@@ -1,66 +1,118 @@
-use std::env;
-
 use crate::commands::common::interaction_error::interaction_error_edit;
+use crate::config::MongoConfig;
 use crate::dbmodels::guild::Guild as GuildStruct;
-use mongodb::bson::doc;
-use mongodb::options::{ClientOptions, CollectionOptions, ResolverConfig};
+use crate::dbmodels::history::HistoryEntry;
+use crate::dbmodels::liked::LikedTrack;
+use crate::dbmodels::metadata_cache::CachedMetadata;
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::{ClientOptions, FindOptions, UpdateOptions};
 use mongodb::*;
+use serenity::futures::TryStreamExt;
 use serenity::model::prelude::interaction::application_command::ApplicationCommandInteraction;
-use serenity::prelude::Context;
+use serenity::model::prelude::UserId;
+use serenity::prelude::{Context, TypeMapKey};
+use songbird::input::Metadata;
+use std::time::Duration;
 use tracing::{error};
 
-pub async fn get_mongo_client(connection_str: &str) -> mongodb::error::Result<Client> {
-    let platform = env::var("PLATFORM").expect("No PLATFORM env set.");
+/// The database of the bot. One is connected at startup and shared through the serenity data,
+/// everything that reads or writes documents goes through it.
+#[derive(Clone)]
+pub struct Db {
+    client: Client,
+    database: Database,
+}
+
+impl Db {
+    pub async fn connect(config: &MongoConfig) -> mongodb::error::Result<Db> {
+        let client_options = match config.resolver.resolver_config() {
+            Some(resolver_config) => {
+                ClientOptions::parse_with_resolver_config(&config.uri, resolver_config).await?
+            }
+            None => ClientOptions::parse(&config.uri).await?,
+        };
+        let client = Client::with_options(client_options)?;
+        Ok(Db {
+            database: client.database(&config.db_name),
+            client,
+        })
+    }
+
+    /// Checks that the server answers.
+    pub async fn ping(&self) -> mongodb::error::Result<()> {
+        self.client
+            .database("admin")
+            .run_command(doc! {"ping": 1}, None)
+            .await?;
+        Ok(())
+    }
+
+    pub fn guilds(&self) -> Collection<GuildStruct> {
+        self.database.collection("guilds")
+    }
+
+    pub fn history(&self) -> Collection<HistoryEntry> {
+        self.database.collection("history")
+    }
+
+    pub fn liked(&self) -> Collection<LikedTrack> {
+        self.database.collection("liked")
+    }
 
-    match platform.as_str() {
-        "windows" => {
-            let client_options = ClientOptions::parse_with_resolver_config(
-                connection_str,
-                ResolverConfig::cloudflare(),
+    pub fn metadata_cache(&self) -> Collection<CachedMetadata> {
+        self.database.collection("metadata_cache")
+    }
+
+    /// Changes how long documents live on an existing TTL index.
+    pub async fn set_expiry(
+        &self,
+        collection: &str,
+        index: &str,
+        expire_after: Duration,
+    ) -> mongodb::error::Result<()> {
+        self.database
+            .run_command(
+                doc! {
+                    "collMod": collection,
+                    "index": {"name": index, "expireAfterSeconds": expire_after.as_secs() as i64},
+                },
+                None,
             )
             .await?;
-            Client::with_options(client_options)
-        }
-        "linux" => {
-            let client_options = ClientOptions::parse(connection_str).await?;
-            Client::with_options(client_options)
-        }
-        _ => {
-            panic!("Invalid PLATFORM env value.")
-        }
+        Ok(())
     }
 }
 
-pub async fn get_db(client: &Client, db_name: &str) -> Database {
-    client.database(db_name)
+/// The database in the serenity data.
+pub struct BotDb;
+
+impl TypeMapKey for BotDb {
+    type Value = Db;
 }
 
-pub async fn get_collection<T>(
-    db: &Database,
-    collection_str: &str,
-    options: Option<CollectionOptions>,
-) -> Collection<T> {
-    let col: Collection<T> = match options {
-        None => db.collection(collection_str),
-        Some(options) => db.collection_with_options(collection_str, options),
-    };
-    col
+/// The shared database, for handlers that only have the `Context`.
+pub async fn db(ctx: &Context) -> Db {
+    ctx.data
+        .read()
+        .await
+        .get::<BotDb>()
+        .cloned()
+        .expect("The database is placed in at initialisation.")
 }
 
 pub async fn get_guild_doc(
-    mongo_client: &mongodb::Client,
+    db: &Db,
     guild_id_str: String,
     interaction: &ApplicationCommandInteraction,
     ctx: &Context,
 ) -> Option<GuildStruct> {
-    let guild_doc_opt: Option<GuildStruct> = match mongo_client
-        .database("botdb")
-        .collection("guilds")
+    let guild_doc_opt: Option<GuildStruct> = match db
+        .guilds()
         .find_one(doc! {"guild_ID": guild_id_str}, None)
         .await
     {
         Ok(col_opt) => match col_opt {
-            Some(col) => col,
+            Some(col) => Some(col),
             None => {
                 interaction_error_edit("Guild is not in database", interaction, ctx).await;
                 return None;
@@ -81,3 +133,117 @@ pub async fn get_guild_doc(
     };
     Some(guild_doc)
 }
+
+/// Adds the track to the user's liked tracks. Returns false if it was already liked.
+pub async fn like_track(
+    db: &Db,
+    user_id: UserId,
+    metadata: &Metadata,
+) -> mongodb::error::Result<bool> {
+    let url = match &metadata.source_url {
+        Some(url) => url.clone(),
+        None => return Ok(false),
+    };
+    let liked = LikedTrack {
+        id: None,
+        user_ID: user_id.0.to_string(),
+        url: url.clone(),
+        title: metadata.title.clone().unwrap_or_default(),
+        artist: metadata.artist.clone().or_else(|| metadata.channel.clone()),
+        duration_secs: metadata.duration.map(|duration| duration.as_secs()),
+        thumbnail: metadata.thumbnail.clone(),
+        liked_at: DateTime::now(),
+    };
+    let liked_doc = bson::to_document(&liked)?;
+
+    let res = db
+        .liked()
+        .update_one(
+            doc! {"user_ID": user_id.0.to_string(), "url": url},
+            doc! {"$setOnInsert": liked_doc},
+            UpdateOptions::builder().upsert(true).build(),
+        )
+        .await?;
+    Ok(res.upserted_id.is_some())
+}
+
+/// Removes the track from the user's liked tracks. Returns false if it wasn't liked.
+pub async fn unlike_track(
+    db: &Db,
+    user_id: UserId,
+    url: &str,
+) -> mongodb::error::Result<bool> {
+    let res = db
+        .liked()
+        .delete_one(doc! {"user_ID": user_id.0.to_string(), "url": url}, None)
+        .await?;
+    Ok(res.deleted_count > 0)
+}
+
+/// All of the user's liked tracks, newest first.
+pub async fn liked_tracks(
+    db: &Db,
+    user_id: UserId,
+) -> mongodb::error::Result<Vec<LikedTrack>> {
+    let options = FindOptions::builder().sort(doc! {"liked_at": -1}).build();
+    db.liked()
+        .find(doc! {"user_ID": user_id.0.to_string()}, options)
+        .await?
+        .try_collect()
+        .await
+}
+
+/// The cached yt-dlp output for the key, however old it is.
+pub async fn cached_metadata(
+    db: &Db,
+    key: &str,
+) -> mongodb::error::Result<Option<CachedMetadata>> {
+    db.metadata_cache()
+        .find_one(doc! {"key": key}, None)
+        .await
+}
+
+/// Saves fresh yt-dlp output for the key, replacing what was there.
+pub async fn store_metadata(
+    db: &Db,
+    key: &str,
+    json: &str,
+) -> mongodb::error::Result<()> {
+    db.metadata_cache()
+        .update_one(
+            doc! {"key": key},
+            doc! {"$set": {"json": json, "fetched_at": DateTime::now()}},
+            UpdateOptions::builder().upsert(true).build(),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Cached entries whose keys start with the prefix, most recently fetched first.
+pub async fn cached_metadata_by_prefix(
+    db: &Db,
+    prefix: &str,
+    limit: i64,
+) -> mongodb::error::Result<Vec<CachedMetadata>> {
+    let options = FindOptions::builder()
+        .sort(doc! {"fetched_at": -1})
+        .limit(limit)
+        .build();
+    let pattern = format!("^{}", regex_escape(prefix));
+    db.metadata_cache()
+        .find(doc! {"key": {"$regex": pattern}}, options)
+        .await?
+        .try_collect()
+        .await
+}
+
+fn regex_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,148 @@
+use mongodb::bson::doc;
+use mongodb::Collection;
+use serenity::futures::TryStreamExt;
+use serenity::model::prelude::{ChannelId, GuildId};
+use serenity::prelude::Context;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::commands::music::play::queue_entries;
+use crate::dbmodels::guild::Guild;
+use crate::mongo_conn::Db;
+use crate::player::join;
+use crate::player::prefetch::PendingEntries;
+
+/// How long to wait before rejoining after being dropped from the channel.
+const REJOIN_DELAY: Duration = Duration::from_secs(5);
+
+pub async fn is_enabled(db: &Db, guild_id: GuildId) -> bool {
+    match guilds(db)
+        .find_one(doc! {"guild_ID": guild_id.0.to_string()}, None)
+        .await
+    {
+        Ok(guild_doc_opt) => guild_doc_opt
+            .map(|guild_doc| guild_doc.always_on)
+            .unwrap_or(false),
+        Err(err) => {
+            error!("{:?}", err);
+            false
+        }
+    }
+}
+
+/// Saves the URLs in the queue so they can be restored after a restart.
+pub async fn save_queue(db: &Db, guild_id: GuildId, urls: &[String]) {
+    if let Err(err) = guilds(db)
+        .update_one(
+            doc! {"guild_ID": guild_id.0.to_string()},
+            doc! {"$set": {"saved_queue": urls}},
+            None,
+        )
+        .await
+    {
+        error!("Failed to save the queue. {:?}", err);
+    }
+}
+
+/// Rejoins the saved channel of every guild that has 24/7 mode on and restores its queue.
+pub async fn rejoin_all(ctx: &Context, db: &Db) {
+    let guild_docs: Vec<Guild> = match guilds(db).find(doc! {"always_on": true}, None).await {
+        Ok(cursor) => match cursor.try_collect().await {
+            Ok(guild_docs) => guild_docs,
+            Err(err) => {
+                error!("{:?}", err);
+                return;
+            }
+        },
+        Err(err) => {
+            error!("{:?}", err);
+            return;
+        }
+    };
+
+    for guild_doc in guild_docs {
+        rejoin(ctx, guild_doc, db).await;
+    }
+}
+
+/// Goes back to the saved channel after the bot was dropped from voice, if the guild is in 24/7
+/// mode. Returns whether it did.
+pub async fn rejoin_if_enabled(ctx: &Context, guild_id: GuildId, db: &Db) -> bool {
+    let guild_doc = match guilds(db)
+        .find_one(doc! {"guild_ID": guild_id.0.to_string()}, None)
+        .await
+    {
+        Ok(Some(guild_doc)) if guild_doc.always_on => guild_doc,
+        Ok(_) => return false,
+        Err(err) => {
+            error!("{:?}", err);
+            return false;
+        }
+    };
+
+    warn!(
+        "Dropped from voice in {} while in 24/7 mode, rejoining",
+        guild_id
+    );
+    tokio::time::sleep(REJOIN_DELAY).await;
+    rejoin(ctx, guild_doc, db).await;
+    true
+}
+
+/// Follows the bot to a new channel if the guild is in 24/7 mode.
+pub async fn update_channel(db: &Db, guild_id: GuildId, channel_id: ChannelId) {
+    if let Err(err) = guilds(db)
+        .update_one(
+            doc! {"guild_ID": guild_id.0.to_string(), "always_on": true},
+            doc! {"$set": {"always_on_channel_ID": channel_id.0.to_string()}},
+            None,
+        )
+        .await
+    {
+        error!("Failed to update the 24/7 channel. {:?}", err);
+    }
+}
+
+async fn rejoin(ctx: &Context, guild_doc: Guild, db: &Db) {
+    let guild_id = match guild_doc.guild_ID.parse::<u64>() {
+        Ok(id) => GuildId(id),
+        Err(_) => return,
+    };
+    let channel_id = match guild_doc.always_on_channel_ID.parse::<u64>() {
+        Ok(id) if id != 0 => ChannelId(id),
+        _ => {
+            warn!("Guild {} is in 24/7 mode without a channel", guild_id);
+            return;
+        }
+    };
+
+    info!("Rejoining {} in {} for 24/7 mode", channel_id, guild_id);
+    let (call_lock, join_res) = join(ctx, guild_id, channel_id, db).await;
+    if let Err(err) = join_res {
+        error!("Failed to rejoin for 24/7 mode. {:?}", err);
+        return;
+    }
+
+    let queue_is_empty = call_lock.lock().await.queue().is_empty();
+    if !queue_is_empty || guild_doc.saved_queue.is_empty() {
+        return;
+    }
+
+    if let Err(err) = queue_entries(
+        ctx,
+        &call_lock,
+        PendingEntries::new(guild_doc.saved_queue, guild_id, db),
+        guild_doc.volume,
+        ctx.cache.current_user_id(),
+        guild_id,
+        db,
+    )
+    .await
+    {
+        error!("Failed to restore the saved queue. {}", err);
+    }
+}
+
+fn guilds(db: &Db) -> Collection<Guild> {
+    db.guilds()
+}
@@ -0,0 +1,148 @@
+use mongodb::bson::doc;
+use serenity::async_trait;
+use serenity::http::Http;
+use serenity::model::prelude::{ChannelId, GuildId};
+use serenity::prelude::TypeMapKey;
+use serenity::utils::Colour;
+use songbird::{Event, EventContext, EventHandler};
+use std::sync::Arc;
+use tracing::error;
+
+use crate::commands::common::player_buttons::player_buttons;
+use crate::dbmodels::guild::AnnounceMode;
+use crate::mongo_conn::Db;
+use crate::player::guild_state::GuildPlayerState;
+use crate::player::{format_duration, TrackRequester};
+
+/// Set on a track's typemap once its start was announced.
+pub struct TrackAnnounced;
+
+impl TypeMapKey for TrackAnnounced {
+    type Value = bool;
+}
+
+/// Posts a now playing message in the guild's announce channel whenever a track starts, and
+/// deletes the previous one first if the guild wants the channel kept tidy.
+pub struct TrackAnnouncer {
+    pub http: Arc<Http>,
+    pub db: Db,
+    pub guild_id: GuildId,
+    pub state: Arc<GuildPlayerState>,
+}
+
+#[async_trait]
+impl EventHandler for TrackAnnouncer {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let tracks = match ctx {
+            EventContext::Track(tracks) => tracks,
+            _ => return None,
+        };
+
+        for (_, track_handle) in tracks.iter() {
+            let requester = {
+                let mut typemap = track_handle.typemap().write().await;
+                // Play also fires when a paused track is resumed, only the first start counts.
+                if typemap.contains_key::<TrackAnnounced>() {
+                    continue;
+                }
+                typemap.insert::<TrackAnnounced>(true);
+                typemap.get::<TrackRequester>().copied()
+            };
+
+            let guild_doc = match self
+                .db
+                .guilds()
+                .find_one(doc! {"guild_ID": self.guild_id.0.to_string()}, None)
+                .await
+            {
+                Ok(Some(guild_doc)) => guild_doc,
+                Ok(None) => return None,
+                Err(err) => {
+                    error!("{:?}", err);
+                    return None;
+                }
+            };
+            let channel_id = match guild_doc.announce_channel_ID.parse::<u64>() {
+                Ok(id) if id != 0 && guild_doc.announce_mode != AnnounceMode::Off => ChannelId(id),
+                _ => return None,
+            };
+
+            let mut last_message = self.state.last_announcement.lock().await;
+            if guild_doc.announce_tidy {
+                if let Some((channel_id, message_id)) = last_message.take() {
+                    if let Err(err) = channel_id.delete_message(&self.http, message_id).await {
+                        error!("Could not delete the previous announcement. {}", err);
+                    }
+                }
+            }
+
+            let metadata = track_handle.metadata();
+            let title = metadata.title.clone().unwrap_or_default();
+            let duration = metadata
+                .duration
+                .map(format_duration)
+                .unwrap_or_else(|| "live".to_string());
+            let requested_by = requester
+                .map(|id| format!("<@{}>", id.0))
+                .unwrap_or_else(|| "someone".to_string());
+
+            let res = channel_id
+                .send_message(&self.http, |message| {
+                    // The requester is only named, not pinged.
+                    message.allowed_mentions(|mentions| mentions.empty_parse());
+                    message.set_components(player_buttons());
+                    match guild_doc.announce_mode {
+                        AnnounceMode::Compact => message.content(format!(
+                            "Now playing **{}** ({}), requested by {}",
+                            title, duration, requested_by
+                        )),
+                        _ => message.embed(|embed| {
+                            embed
+                                .title("Now Playing")
+                                .description(match &metadata.source_url {
+                                    Some(url) => format!("[{}]({})", title, url),
+                                    None => title.clone(),
+                                })
+                                .field("Requested by", &requested_by, true)
+                                .field("Duration", &duration, true)
+                                .color(Colour::from_rgb(0, 150, 255));
+                            if let Some(thumbnail) = &metadata.thumbnail {
+                                embed.thumbnail(thumbnail);
+                            }
+                            embed
+                        }),
+                    }
+                })
+                .await;
+            match res {
+                Ok(message) => *last_message = Some((channel_id, message.id)),
+                Err(err) => error!("Could not send the track announcement. {}", err),
+            }
+        }
+        None
+    }
+}
+
+/// Where notices about the guild's playback go: its announce channel if it set one, otherwise
+/// `fallback`, usually the voice channel.
+pub async fn notice_channel(
+    db: &Db,
+    guild_id: GuildId,
+    fallback: Option<ChannelId>,
+) -> Option<ChannelId> {
+    let guild_doc = match db
+        .guilds()
+        .find_one(doc! {"guild_ID": guild_id.0.to_string()}, None)
+        .await
+    {
+        Ok(guild_doc) => guild_doc,
+        Err(err) => {
+            error!("{:?}", err);
+            None
+        }
+    };
+    match guild_doc.map(|guild_doc| guild_doc.announce_channel_ID.parse::<u64>()) {
+        Some(Ok(id)) if id != 0 => Some(ChannelId(id)),
+        _ => fallback,
+    }
+}
@@ -0,0 +1,396 @@
+use mongodb::bson::doc;
+use serenity::async_trait;
+use serenity::model::prelude::GuildId;
+use serenity::prelude::TypeMapKey;
+use songbird::tracks::PlayMode;
+use songbird::{Event, EventContext, EventHandler};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+use tokio::process::Command as TokioCommand;
+use tracing::{error, info, warn};
+
+use crate::config::config;
+use crate::mongo_conn::Db;
+use crate::player::governor::governor;
+use crate::player::ytdl::extractor;
+
+/// Tracks that stopped further than this from their end were not fully played.
+const END_TOLERANCE: Duration = Duration::from_secs(5);
+
+/// The cache key of the track, set on tracks of guilds that use the cache and not read from it.
+pub struct TrackCacheKey;
+
+impl TypeMapKey for TrackCacheKey {
+    type Value = String;
+}
+
+/// Transcoded audio of played tracks, kept on disk up to a size limit. The least recently played
+/// files are removed first.
+pub struct AudioCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    filling: Mutex<HashSet<String>>,
+}
+
+/// What `/cache stats` shows.
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub files: u64,
+    pub bytes: u64,
+    pub max_bytes: u64,
+}
+
+/// The disk cache, if `cache.dir` is set and the feature is on. `cache.max_mb` limits its size.
+pub fn audio_cache() -> Option<&'static AudioCache> {
+    static CACHE: OnceLock<Option<AudioCache>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| {
+            if !config().features.audio_cache {
+                return None;
+            }
+            let dir = config().cache.dir.clone()?;
+            let max_mb = config().cache.max_mb;
+            if let Err(err) = std::fs::create_dir_all(&dir) {
+                error!(
+                    "Could not create the audio cache directory {}. {}",
+                    dir, err
+                );
+                return None;
+            }
+            info!("Caching audio in {} up to {} MB.", dir, max_mb);
+            Some(AudioCache::new(PathBuf::from(dir), max_mb * 1024 * 1024))
+        })
+        .as_ref()
+}
+
+/// Whether the guild plays from and adds to the disk cache.
+pub async fn is_enabled(db: &Db, guild_id: GuildId) -> bool {
+    match db
+        .guilds()
+        .find_one(doc! {"guild_ID": guild_id.0.to_string()}, None)
+        .await
+    {
+        Ok(guild_doc_opt) => guild_doc_opt
+            .map(|guild_doc| guild_doc.audio_cache)
+            .unwrap_or(false),
+        Err(err) => {
+            error!("{:?}", err);
+            false
+        }
+    }
+}
+
+impl AudioCache {
+    fn new(dir: PathBuf, max_bytes: u64) -> AudioCache {
+        AudioCache {
+            dir,
+            max_bytes,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            filling: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.opus", key))
+    }
+
+    /// The cached file for the key, marked as just used.
+    pub async fn lookup(&self, key: &str) -> Option<PathBuf> {
+        let path = self.path(key);
+        let touch_path = path.clone();
+        // The modification time is what eviction goes by.
+        let touched = tokio::task::spawn_blocking(move || {
+            std::fs::File::options()
+                .append(true)
+                .open(&touch_path)
+                .and_then(|file| file.set_modified(SystemTime::now()))
+        })
+        .await;
+
+        match touched {
+            Ok(Ok(())) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(path)
+            }
+            _ => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Downloads and transcodes the track into the cache, then makes room for it. The transcode
+    /// takes one of the guild's ffmpeg slots.
+    ///
+    /// The track is downloaded a second time for this, after it played to the end. Writing the
+    /// playing stream to disk instead would break on every seek, which restarts the stream at the
+    /// new position, so a cached file costs one extra download of the track.
+    pub async fn fill(&self, key: String, url: String, guild_id: GuildId) {
+        let _filling = match self.start_filling(&key) {
+            Some(filling) => filling,
+            None => return,
+        };
+
+        let path = self.path(&key);
+        let part_path = self.dir.join(format!("{}.part", key));
+        let permit = governor().acquire(guild_id).await;
+        let transcoded = transcode(&url, &part_path).await;
+        drop(permit);
+        match transcoded {
+            Ok(()) => match tokio::fs::rename(&part_path, &path).await {
+                Ok(()) => {
+                    info!("Cached {} as {:?}.", url, path);
+                    self.evict().await;
+                }
+                Err(err) => error!("Could not move {:?} into the cache. {}", part_path, err),
+            },
+            Err(err) => {
+                warn!("Could not cache {}. {}", url, err);
+                let _res = tokio::fs::remove_file(&part_path).await;
+            }
+        }
+    }
+
+    /// Marks the key as being filled until the guard is dropped, None if it already is.
+    fn start_filling(&self, key: &str) -> Option<Filling<'_>> {
+        let mut filling = self.filling.lock().unwrap_or_else(|err| err.into_inner());
+        if !filling.insert(key.to_string()) {
+            return None;
+        }
+        Some(Filling {
+            filling: &self.filling,
+            key: key.to_string(),
+        })
+    }
+
+    /// Removes the least recently played files until the cache fits in its limit.
+    async fn evict(&self) {
+        let mut files = self.files().await;
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        if total <= self.max_bytes {
+            return;
+        }
+
+        files.sort_by_key(|(_, _, modified)| *modified);
+        for (path, size, _) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => {
+                    info!("Evicted {:?} from the audio cache.", path);
+                    total -= size;
+                }
+                Err(err) => error!("Could not evict {:?}. {}", path, err),
+            }
+        }
+    }
+
+    /// The finished files with their sizes and last use.
+    async fn files(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+        let mut files = vec![];
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(err) => {
+                error!("Could not read the audio cache {:?}. {}", self.dir, err);
+                return files;
+            }
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("opus") {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata().await {
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                files.push((path, metadata.len(), modified));
+            }
+        }
+        files
+    }
+
+    pub async fn stats(&self) -> CacheStats {
+        let files = self.files().await;
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            files: files.len() as u64,
+            bytes: files.iter().map(|(_, size, _)| size).sum(),
+            max_bytes: self.max_bytes,
+        }
+    }
+}
+
+/// A key that is being filled. Dropping it frees the key again, also when the fill panicked or
+/// was cancelled.
+struct Filling<'a> {
+    filling: &'a Mutex<HashSet<String>>,
+    key: String,
+}
+
+impl Drop for Filling<'_> {
+    fn drop(&mut self) {
+        self.filling
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(&self.key);
+    }
+}
+
+/// Runs yt-dlp into ffmpeg, writing the audio as Opus.
+async fn transcode(url: &str, out_path: &std::path::Path) -> std::io::Result<()> {
+    let mut youtube_dl = extractor()
+        .stream_command()
+        .args(["--no-playlist", url, "-o", "-"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdout = match youtube_dl.stdout.take() {
+        Some(stdout) => stdout,
+        None => return Err(std::io::Error::other("yt-dlp has no stdout")),
+    };
+
+    let status = TokioCommand::new("ffmpeg")
+        .args([
+            "-y", "-i", "-", "-vn", "-c:a", "libopus", "-b:a", "96k", "-f", "ogg",
+        ])
+        .arg(out_path)
+        .stdin(stdout)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await?;
+    let _res = tokio::task::spawn_blocking(move || youtube_dl.wait()).await;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "ffmpeg exited with {}",
+            status
+        )))
+    }
+}
+
+/// Caches tracks that were played to the end, only set on tracks that came from the network.
+pub struct CacheFiller {
+    pub guild_id: GuildId,
+}
+
+#[async_trait]
+impl EventHandler for CacheFiller {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let tracks = match ctx {
+            EventContext::Track(tracks) => tracks,
+            _ => return None,
+        };
+        let cache = audio_cache()?;
+
+        for (track_state, track_handle) in tracks.iter() {
+            if track_state.playing != PlayMode::End {
+                continue;
+            }
+            let duration = match track_handle.metadata().duration {
+                Some(duration) => duration,
+                None => continue,
+            };
+            if track_state.position + END_TOLERANCE < duration {
+                continue;
+            }
+
+            let key = track_handle
+                .typemap()
+                .read()
+                .await
+                .get::<TrackCacheKey>()
+                .cloned();
+            if let (Some(key), Some(url)) = (key, track_handle.metadata().source_url.clone()) {
+                tokio::spawn(cache.fill(key, url, self.guild_id));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cache in a fresh directory, with files of the given size written oldest first.
+    fn cache_with(name: &str, max_bytes: u64, keys: &[&str], size: usize) -> AudioCache {
+        let dir =
+            std::env::temp_dir().join(format!("ironingot-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let cache = AudioCache::new(dir, max_bytes);
+        let start = SystemTime::now() - Duration::from_secs(3600);
+        for (age, key) in keys.iter().enumerate() {
+            let file = std::fs::File::create(cache.path(key)).unwrap();
+            file.set_len(size as u64).unwrap();
+            file.set_modified(start + Duration::from_secs(age as u64 * 60))
+                .unwrap();
+        }
+        cache
+    }
+
+    fn cached_keys(cache: &AudioCache) -> Vec<String> {
+        let mut keys: Vec<String> = std::fs::read_dir(&cache.dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    #[tokio::test]
+    async fn evicts_the_least_recently_played_first() {
+        let cache = cache_with("order", 1000, &["a", "b", "c"], 400);
+        cache.evict().await;
+        assert_eq!(cached_keys(&cache), ["b.opus", "c.opus"]);
+        std::fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn playing_a_file_keeps_it_longer() {
+        let cache = cache_with("lookup", 800, &["a", "b", "c"], 400);
+        assert!(cache.lookup("a").await.is_some());
+        assert!(cache.lookup("missing").await.is_none());
+        cache.evict().await;
+        assert_eq!(cached_keys(&cache), ["a.opus", "c.opus"]);
+
+        let stats = cache.stats().await;
+        assert_eq!((stats.hits, stats.misses, stats.files), (1, 1, 2));
+        std::fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn a_key_is_filled_once_at_a_time() {
+        let cache = cache_with("filling", 400, &[], 0);
+        let filling = cache.start_filling("a").unwrap();
+        assert!(cache.start_filling("a").is_none());
+        assert!(cache.start_filling("b").is_some());
+        drop(filling);
+        assert!(cache.start_filling("a").is_some());
+        std::fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn unfinished_files_are_left_alone() {
+        let cache = cache_with("part", 400, &["a", "b"], 400);
+        std::fs::write(cache.dir.join("c.part"), vec![0; 4000]).unwrap();
+        cache.evict().await;
+        assert_eq!(cached_keys(&cache), ["b.opus", "c.part"]);
+        std::fs::remove_dir_all(&cache.dir).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+use serenity::model::prelude::{ChannelId, GuildId};
+use serenity::prelude::{Context, Mutex, TypeMapKey};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::config::config;
+use crate::mongo_conn::Db;
+use crate::player::always_on;
+
+/// Guilds whose playback was paused because no listeners were left, with a token that tells a
+/// pending leave whether it is still the latest one.
+pub struct EmptyChannelPauses;
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+impl TypeMapKey for EmptyChannelPauses {
+    type Value = Arc<Mutex<HashMap<GuildId, u64>>>;
+}
+
+/// Reacts to a user's voice state changing in a guild the bot is connected to. When the last
+/// human leaves the bot's channel, by disconnecting or by moving away, playback is paused and
+/// the bot leaves once the grace period is over. A human joining in time resumes playback.
+pub async fn update(
+    ctx: &Context,
+    guild_id: GuildId,
+    old_channel: Option<ChannelId>,
+    new_channel: Option<ChannelId>,
+    db: &Db,
+) {
+    // Mutes, deafens and the like don't move anyone.
+    if old_channel == new_channel {
+        return;
+    }
+
+    let bot_id = ctx.cache.current_user_id();
+    let bot_channel = match ctx
+        .cache
+        .guild_field(guild_id, |guild| {
+            guild
+                .voice_states
+                .get(&bot_id)
+                .and_then(|state| state.channel_id)
+        })
+        .flatten()
+    {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+
+    let pauses = match ctx.data.read().await.get::<EmptyChannelPauses>() {
+        Some(pauses) => pauses.clone(),
+        None => {
+            error!("Empty channel pauses were not placed in at initialisation.");
+            return;
+        }
+    };
+
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+    let call_lock = match manager.get(guild_id) {
+        Some(call_lock) => call_lock,
+        None => return,
+    };
+
+    if new_channel == Some(bot_channel) {
+        // Other bots joining don't count as listeners, the pending leave stays.
+        if !pauses.lock().await.contains_key(&guild_id)
+            || human_count(ctx, bot_channel).await.unwrap_or(0) == 0
+        {
+            return;
+        }
+        if pauses.lock().await.remove(&guild_id).is_some() {
+            info!("A listener came back in {}, resuming", guild_id);
+            if let Err(err) = call_lock.lock().await.queue().resume() {
+                error!("Failed to resume. {}", err);
+            }
+        }
+        return;
+    }
+
+    if old_channel != Some(bot_channel) || human_count(ctx, bot_channel).await != Some(0) {
+        return;
+    }
+
+    info!("No listeners left in {}, pausing", guild_id);
+    if let Err(err) = call_lock.lock().await.queue().pause() {
+        error!("Failed to pause. {}", err);
+    }
+
+    let token = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+    pauses.lock().await.insert(guild_id, token);
+
+    // 24/7 mode never leaves, the pause is all that happens.
+    if always_on::is_enabled(db, guild_id).await {
+        return;
+    }
+
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(grace_period()).await;
+
+        {
+            let mut pauses = pauses.lock().await;
+            if pauses.get(&guild_id) != Some(&token) {
+                return;
+            }
+            pauses.remove(&guild_id);
+        }
+
+        info!("Nobody came back to {}, leaving", guild_id);
+        let manager = songbird::get(&ctx)
+            .await
+            .expect("Songbird Voice client placed in at initialisation.")
+            .clone();
+        if let Err(err) = manager.remove(guild_id).await {
+            error!("Failed to leave the empty channel. {:?}", err);
+        }
+    });
+}
+
+async fn human_count(ctx: &Context, channel_id: ChannelId) -> Option<usize> {
+    let guild_channel = match channel_id.to_channel(ctx).await {
+        Ok(channel) => match channel.guild() {
+            Some(guild_channel) => guild_channel,
+            None => {
+                warn!("No guild for the bot's voice channel");
+                return None;
+            }
+        },
+        Err(_) => {
+            error!("Failed to get guild_channel from channel");
+            return None;
+        }
+    };
+
+    match guild_channel.members(ctx).await {
+        Ok(members) => Some(members.iter().filter(|member| !member.user.bot).count()),
+        Err(_) => {
+            error!("Failed to get members from guild_channel");
+            None
+        }
+    }
+}
+
+/// How long the bot waits for a listener to come back, from `player.empty_channel_grace_secs`.
+fn grace_period() -> Duration {
+    Duration::from_secs(config().player.empty_channel_grace_secs)
+}
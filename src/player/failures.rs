@@ -0,0 +1,288 @@
+use serenity::async_trait;
+use serenity::http::Http;
+use serenity::model::prelude::{ChannelId, GuildId};
+use serenity::prelude::{Mutex, TypeMapKey};
+use serenity::utils::Colour;
+use songbird::input::error::Error as InputError;
+use songbird::tracks::PlayMode;
+use songbird::{Event, EventContext, EventHandler, Songbird};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+
+use crate::mongo_conn::Db;
+use crate::player::announce::notice_channel;
+use crate::player::guild_state::GuildPlayerState;
+use crate::player::source::{resolve, StreamError, TrackStreamError};
+use crate::player::ytdl_error::YtdlError;
+use crate::player::{enqueue, register_call_events, TrackRequester};
+
+/// How many tracks in a row may fail before the queue is stopped.
+pub const MAX_CONSECUTIVE_FAILURES: u32 = 5;
+
+/// A track that ends this close to its duration finished normally.
+const END_TOLERANCE: Duration = Duration::from_secs(5);
+
+/// Set on a track's typemap when it is the retry of a failed track, so it is retried only once.
+pub struct TrackRetried;
+
+impl TypeMapKey for TrackRetried {
+    type Value = bool;
+}
+
+/// Why a track could not be played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureReason {
+    /// yt-dlp failed, for the reason it printed.
+    Ytdl(YtdlError),
+    /// yt-dlp ran but its output could not be used.
+    Extraction,
+    /// The stream ended before any audio was played.
+    NoAudio,
+    /// The stream stopped partway through.
+    CutOff(Duration),
+}
+
+impl FailureReason {
+    pub fn from_input_error(err: &InputError) -> FailureReason {
+        if let Some(ytdl_error) = YtdlError::from_input_error(err) {
+            return FailureReason::Ytdl(ytdl_error);
+        }
+        match err {
+            InputError::Io(_) | InputError::Stdout => FailureReason::Ytdl(YtdlError::Network),
+            _ => FailureReason::Extraction,
+        }
+    }
+
+    /// Whether trying the same track again might work.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            FailureReason::Ytdl(ytdl_error) => ytdl_error.is_transient(),
+            FailureReason::NoAudio | FailureReason::CutOff(_) => true,
+            FailureReason::Extraction => false,
+        }
+    }
+}
+
+impl Display for FailureReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FailureReason::Ytdl(ytdl_error) => write!(f, "{}", ytdl_error),
+            FailureReason::Extraction => write!(f, "the source could not be read"),
+            FailureReason::NoAudio => write!(f, "the stream had no audio"),
+            FailureReason::CutOff(at) => write!(f, "the stream broke off at {}s", at.as_secs()),
+        }
+    }
+}
+
+/// Watches every track of a call for ending before its time. A broken track is retried once if
+/// the failure looks transient, otherwise the queue moves on and a notice goes to the announce
+/// channel, or the voice channel if there is none. The queue is stopped once too many tracks
+/// failed in a row.
+///
+/// Songbird 0.3.2 has no `TrackEvent::Error`, a broken stream just ends the track. Failures are
+/// guessed from `TrackEvent::End` instead: a track that played less than a second had no audio,
+/// one that ended well before its duration was cut off.
+pub struct TrackFailureHandler {
+    pub manager: Arc<Songbird>,
+    pub http: Arc<Http>,
+    pub guild_id: GuildId,
+    pub db: Db,
+    pub previous: Arc<Mutex<HashMap<GuildId, VecDeque<String>>>>,
+    pub state: Arc<GuildPlayerState>,
+}
+
+#[async_trait]
+impl EventHandler for TrackFailureHandler {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let tracks = match ctx {
+            EventContext::Track(tracks) => tracks,
+            _ => return None,
+        };
+
+        for (state, track_handle) in tracks.iter() {
+            // Skips and stops end the track with Stop, only natural ends can be failures.
+            if state.playing != PlayMode::End {
+                continue;
+            }
+
+            let metadata = track_handle.metadata();
+            let reason = if state.position < Duration::from_secs(1) {
+                FailureReason::NoAudio
+            } else {
+                match metadata.duration {
+                    Some(duration) if state.position + END_TOLERANCE < duration => {
+                        FailureReason::CutOff(state.position)
+                    }
+                    _ => {
+                        self.state.failures.store(0, Ordering::Relaxed);
+                        continue;
+                    }
+                }
+            };
+
+            // What yt-dlp printed beats the guess from the position.
+            let stream_error = track_handle
+                .typemap()
+                .read()
+                .await
+                .get::<TrackStreamError>()
+                .and_then(StreamError::get);
+            let reason = stream_error.map_or(reason, FailureReason::Ytdl);
+
+            let title = metadata.title.clone().unwrap_or_default();
+            warn!(
+                "Track {:?} in {} failed: {:?}",
+                title, self.guild_id, reason
+            );
+
+            let call_lock = match self.manager.get(self.guild_id) {
+                Some(call_lock) => call_lock,
+                None => return None,
+            };
+            let voice_channel_id = call_lock
+                .lock()
+                .await
+                .current_channel()
+                .map(|channel| ChannelId(channel.0));
+            let channel_id = notice_channel(&self.db, self.guild_id, voice_channel_id).await;
+
+            if self.state.failures.fetch_add(1, Ordering::Relaxed) + 1 >= MAX_CONSECUTIVE_FAILURES {
+                // Drop the pending playlist entries first, stopping would queue the next one.
+                let mut call = call_lock.lock().await;
+                call.remove_all_global_events();
+                register_call_events(
+                    &mut call,
+                    &self.manager,
+                    &self.http,
+                    self.guild_id,
+                    &self.db,
+                    &self.previous,
+                    &self.state,
+                );
+                call.queue().stop();
+                drop(call);
+                give_up(&self.http, channel_id).await;
+                return None;
+            }
+
+            let typemap = track_handle.typemap().read().await;
+            let retried = typemap.get::<TrackRetried>().copied().unwrap_or(false);
+            let requester = typemap.get::<TrackRequester>().copied();
+            drop(typemap);
+
+            let retrying = match (&metadata.source_url, requester) {
+                (Some(url), Some(requester)) if reason.is_transient() && !retried => {
+                    self.retry(url, state.volume, requester).await
+                }
+                _ => false,
+            };
+            report(&self.http, channel_id, &title, &reason, retrying).await;
+        }
+        None
+    }
+}
+
+impl TrackFailureHandler {
+    /// Queues the track again right after the current one. Returns whether that worked.
+    async fn retry(
+        &self,
+        url: &str,
+        volume: f32,
+        requester: serenity::model::prelude::UserId,
+    ) -> bool {
+        let source = match resolve(url, self.guild_id, &self.db).await {
+            Ok(source) => source,
+            Err(err) => {
+                warn!("Retrying {} failed. {}", url, err);
+                return false;
+            }
+        };
+        let call_lock = match self.manager.get(self.guild_id) {
+            Some(call_lock) => call_lock,
+            None => return false,
+        };
+
+        let mut call = call_lock.lock().await;
+        let track_handle = enqueue(
+            &mut call,
+            source,
+            volume,
+            requester,
+            self.guild_id,
+            &self.db,
+        )
+        .await;
+        track_handle
+            .typemap()
+            .write()
+            .await
+            .insert::<TrackRetried>(true);
+        call.queue().modify_queue(|queue| {
+            if queue.len() > 2 {
+                if let Some(track) = queue.pop_back() {
+                    queue.insert(1, track);
+                }
+            }
+        });
+        true
+    }
+}
+
+/// Posts a short notice naming the track that failed and why.
+pub async fn report(
+    http: &Http,
+    channel_id: Option<ChannelId>,
+    title: &str,
+    reason: &FailureReason,
+    retrying: bool,
+) {
+    let channel_id = match channel_id {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+    let res = channel_id
+        .send_message(http, |message| {
+            message.embed(|embed| {
+                embed
+                    .title(if retrying {
+                        "Retrying Track"
+                    } else {
+                        "Skipped Track"
+                    })
+                    .description(format!("**{}** failed, {}.", title, reason))
+                    .color(Colour::from_rgb(255, 165, 0))
+            })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("Could not send the track failure notice. {}", err);
+    }
+}
+
+/// Posts the notice for when the queue was stopped because too many tracks failed.
+pub async fn give_up(http: &Http, channel_id: Option<ChannelId>) {
+    let channel_id = match channel_id {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+    let res = channel_id
+        .send_message(http, |message| {
+            message.embed(|embed| {
+                embed
+                    .title("Playback Stopped")
+                    .description(format!(
+                        "{} tracks in a row could not be played, the queue was stopped.",
+                        MAX_CONSECUTIVE_FAILURES
+                    ))
+                    .color(Colour::from_rgb(255, 0, 0))
+            })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("Could not send the track failure notice. {}", err);
+    }
+}
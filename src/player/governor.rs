@@ -0,0 +1,368 @@
+use serenity::model::prelude::GuildId;
+use songbird::constants::{CHILD_BUFFER_LEN, STEREO_FRAME_SIZE};
+use songbird::input::reader::MediaSource;
+use songbird::input::{ChildContainer, Reader};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::mem;
+use std::process::Child;
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::oneshot;
+use tracing::{debug, info};
+
+use crate::config::config;
+
+/// Caps how many ffmpeg pipelines run at once across all guilds. A guild that has to wait is let
+/// in ahead of guilds already using more slots than it, so one busy server can't take them all.
+pub struct Governor {
+    max: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    active: HashMap<GuildId, usize>,
+    total: usize,
+    waiting: VecDeque<(GuildId, oneshot::Sender<()>)>,
+}
+
+/// The current use of the governor.
+#[derive(Debug, Clone, Copy)]
+pub struct Usage {
+    pub active: usize,
+    pub waiting: usize,
+    pub max: usize,
+}
+
+/// The governor, limited by `player.ffmpeg_max_concurrent`.
+pub fn governor() -> &'static Governor {
+    static GOVERNOR: OnceLock<Governor> = OnceLock::new();
+    GOVERNOR.get_or_init(|| {
+        let max = config().player.ffmpeg_max_concurrent;
+        info!("Running up to {} ffmpeg pipelines at once.", max);
+        Governor::new(max)
+    })
+}
+
+impl Governor {
+    fn new(max: usize) -> Governor {
+        Governor {
+            max,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Waits for a slot for the guild.
+    pub async fn acquire(&'static self, guild_id: GuildId) -> AudioPermit {
+        let admitted = {
+            let mut state = self.state.lock().unwrap();
+            if state.total < self.max && state.waiting.is_empty() {
+                state.admit(guild_id);
+                self.report(&state);
+                None
+            } else {
+                let (sender, receiver) = oneshot::channel();
+                state.waiting.push_back((guild_id, sender));
+                info!(
+                    active = state.total,
+                    waiting = state.waiting.len(),
+                    max = self.max,
+                    "Guild {} is waiting for an ffmpeg slot.",
+                    guild_id
+                );
+                Some(receiver)
+            }
+        };
+        if let Some(receiver) = admitted {
+            let mut waiter = Waiter {
+                governor: self,
+                guild_id,
+                receiver: Some(receiver),
+            };
+            // The slot was already counted for us by `release`.
+            if let Some(receiver) = &mut waiter.receiver {
+                receiver
+                    .await
+                    .expect("Waiters are only dropped once they were admitted.");
+            }
+            // The permit takes the slot over from here.
+            waiter.receiver = None;
+        }
+        AudioPermit {
+            governor: self,
+            guild_id,
+        }
+    }
+
+    /// Takes a slot right away, even past the limit. For seeks, which replace a pipeline that
+    /// already holds a slot and must not wait behind other guilds.
+    pub fn acquire_now(&'static self, guild_id: GuildId) -> AudioPermit {
+        let mut state = self.state.lock().unwrap();
+        state.admit(guild_id);
+        self.report(&state);
+        AudioPermit {
+            governor: self,
+            guild_id,
+        }
+    }
+
+    /// Whether new pipelines have to wait for a slot.
+    pub fn is_saturated(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.total >= self.max || !state.waiting.is_empty()
+    }
+
+    pub fn usage(&self) -> Usage {
+        let state = self.state.lock().unwrap();
+        Usage {
+            active: state.total,
+            waiting: state.waiting.len(),
+            max: self.max,
+        }
+    }
+
+    fn release(&self, guild_id: GuildId) {
+        let mut state = self.state.lock().unwrap();
+        state.forget(guild_id);
+
+        while state.total < self.max {
+            // The waiting guild with the fewest slots goes first, the earliest among equals.
+            let next = state
+                .waiting
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, (guild_id, _))| state.active.get(guild_id).copied().unwrap_or(0))
+                .map(|(index, _)| index);
+            let (guild_id, sender) = match next.and_then(|index| state.waiting.remove(index)) {
+                Some(waiter) => waiter,
+                None => break,
+            };
+            // A waiter that gave up has dropped its receiver, its slot goes to the next one.
+            if sender.is_closed() {
+                continue;
+            }
+            state.admit(guild_id);
+            if sender.send(()).is_err() {
+                state.forget(guild_id);
+            }
+        }
+        self.report(&state);
+    }
+
+    fn report(&self, state: &State) {
+        debug!(
+            active = state.total,
+            waiting = state.waiting.len(),
+            guilds = state.active.len(),
+            max = self.max,
+            "ffmpeg slots in use."
+        );
+    }
+}
+
+impl State {
+    fn admit(&mut self, guild_id: GuildId) {
+        *self.active.entry(guild_id).or_insert(0) += 1;
+        self.total += 1;
+    }
+
+    fn forget(&mut self, guild_id: GuildId) {
+        if let Some(count) = self.active.get_mut(&guild_id) {
+            *count -= 1;
+            if *count == 0 {
+                self.active.remove(&guild_id);
+            }
+        }
+        self.total = self.total.saturating_sub(1);
+    }
+}
+
+/// A guild waiting in `acquire`. When the wait is cancelled, the guild leaves the queue, or gives
+/// its slot on if it was admitted in the meantime.
+struct Waiter {
+    governor: &'static Governor,
+    guild_id: GuildId,
+    receiver: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        let mut receiver = match self.receiver.take() {
+            Some(receiver) => receiver,
+            None => return,
+        };
+        receiver.close();
+        if receiver.try_recv().is_ok() {
+            self.governor.release(self.guild_id);
+        } else {
+            let mut state = self.governor.state.lock().unwrap();
+            state.waiting.retain(|(_, sender)| !sender.is_closed());
+        }
+    }
+}
+
+/// A slot of the governor, given back when dropped.
+pub struct AudioPermit {
+    governor: &'static Governor,
+    guild_id: GuildId,
+}
+
+impl Drop for AudioPermit {
+    fn drop(&mut self) {
+        self.governor.release(self.guild_id);
+    }
+}
+
+/// Reads the output of a pipeline of f32 PCM like `children_to_reader`, keeping the permit for as
+/// long as the processes are alive.
+pub fn governed(children: Vec<Child>, permit: AudioPermit) -> Reader {
+    Reader::Extension(Box::new(Governed {
+        reader: BufReader::with_capacity(
+            STEREO_FRAME_SIZE * mem::size_of::<f32>() * CHILD_BUFFER_LEN,
+            ChildContainer::new(children),
+        ),
+        _permit: permit,
+    }))
+}
+
+struct Governed {
+    reader: BufReader<ChildContainer>,
+    _permit: AudioPermit,
+}
+
+impl Read for Governed {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Seek for Governed {
+    fn seek(&mut self, _pos: SeekFrom) -> std::io::Result<u64> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "pipes can't be seeked",
+        ))
+    }
+}
+
+impl MediaSource for Governed {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::futures::FutureExt;
+    use std::time::Duration;
+    use tokio::task::JoinHandle;
+
+    fn leaked(max: usize) -> &'static Governor {
+        Box::leak(Box::new(Governor::new(max)))
+    }
+
+    /// Starts waiting for a slot and returns once the governor queued the guild.
+    async fn wait_for_slot(governor: &'static Governor, guild_id: u64) -> JoinHandle<AudioPermit> {
+        let waiting = governor.usage().waiting;
+        let handle = tokio::spawn(governor.acquire(GuildId(guild_id)));
+        while governor.usage().waiting == waiting {
+            tokio::task::yield_now().await;
+        }
+        handle
+    }
+
+    async fn admitted(handle: JoinHandle<AudioPermit>) -> AudioPermit {
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("The waiter was admitted.")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn waits_once_all_slots_are_taken() {
+        let governor = leaked(2);
+        let first = governor.acquire(GuildId(1)).await;
+        let _second = governor.acquire(GuildId(2)).await;
+        assert!(governor.is_saturated());
+
+        let third = wait_for_slot(governor, 3).await;
+        assert!(!third.is_finished());
+        drop(first);
+        let _third = admitted(third).await;
+        let usage = governor.usage();
+        assert_eq!((usage.active, usage.waiting), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn guild_with_fewer_slots_goes_first() {
+        let governor = leaked(2);
+        let busy = governor.acquire(GuildId(1)).await;
+        let _busy = governor.acquire(GuildId(1)).await;
+
+        // The busy guild asked first, but already holds a slot where the other holds none.
+        let busy_waiter = wait_for_slot(governor, 1).await;
+        let quiet_waiter = wait_for_slot(governor, 2).await;
+        drop(busy);
+        let _quiet = admitted(quiet_waiter).await;
+        assert!(!busy_waiter.is_finished());
+        assert_eq!(governor.usage().waiting, 1);
+        busy_waiter.abort();
+    }
+
+    #[tokio::test]
+    async fn abandoned_waiters_pass_their_slot_on() {
+        let governor = leaked(1);
+        let held = governor.acquire(GuildId(1)).await;
+        let abandoned = wait_for_slot(governor, 2).await;
+        let waiter = wait_for_slot(governor, 3).await;
+        abandoned.abort();
+        let _ = abandoned.await;
+
+        drop(held);
+        let _admitted = admitted(waiter).await;
+        let usage = governor.usage();
+        assert_eq!((usage.active, usage.waiting), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiters_leave_the_queue() {
+        let governor = leaked(1);
+        let _held = governor.acquire(GuildId(1)).await;
+        let mut waiter = Box::pin(governor.acquire(GuildId(2)));
+        assert!((&mut waiter).now_or_never().is_none());
+        assert_eq!(governor.usage().waiting, 1);
+
+        drop(waiter);
+        assert_eq!(governor.usage().waiting, 0);
+    }
+
+    #[tokio::test]
+    async fn waiters_dropped_after_admission_give_the_slot_back() {
+        let governor = leaked(1);
+        let held = governor.acquire(GuildId(1)).await;
+        let mut waiter = Box::pin(governor.acquire(GuildId(2)));
+        assert!((&mut waiter).now_or_never().is_none());
+
+        // Admitted, but never polled again to take the permit.
+        drop(held);
+        assert_eq!(governor.usage().active, 1);
+        drop(waiter);
+        let usage = governor.usage();
+        assert_eq!((usage.active, usage.waiting), (0, 0));
+    }
+
+    #[test]
+    fn seeks_are_admitted_past_the_limit() {
+        let governor = leaked(1);
+        let _playing = governor.acquire_now(GuildId(1));
+        let seek = governor.acquire_now(GuildId(1));
+        assert_eq!(governor.usage().active, 2);
+        drop(seek);
+        assert_eq!(governor.usage().active, 1);
+    }
+}
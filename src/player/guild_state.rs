@@ -0,0 +1,55 @@
+use serenity::model::prelude::{ChannelId, GuildId, MessageId};
+use serenity::prelude::{Context, Mutex, TypeMapKey};
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU32;
+use std::sync::Arc;
+
+use crate::player::prefetch::PendingEntries;
+
+/// What the call's global handlers keep about a guild. The handlers are dropped and registered
+/// again whenever the global events are cleared, so anything that has to outlive that lives here.
+#[derive(Default)]
+pub struct GuildPlayerState {
+    /// The last now playing message, deleted before the next one when the guild wants it tidy.
+    pub last_announcement: Mutex<Option<(ChannelId, MessageId)>>,
+    /// How many tracks failed in a row.
+    pub failures: AtomicU32,
+    /// The playlists whose remaining entries are queued one by one.
+    pub playlists: Mutex<Vec<Arc<PendingEntries>>>,
+}
+
+impl GuildPlayerState {
+    /// Keeps track of a playlist so editing the queue can cancel it.
+    pub async fn add_playlist(&self, pending: Arc<PendingEntries>) {
+        let mut playlists = self.playlists.lock().await;
+        playlists.retain(|pending| !pending.is_cancelled());
+        playlists.push(pending);
+    }
+
+    /// Drops the remaining entries of the playlists, along with what was prefetched for them.
+    pub async fn cancel_playlists(&self) {
+        for pending in self.playlists.lock().await.drain(..) {
+            pending.cancel();
+        }
+    }
+}
+
+/// The player state of every guild the bot played in.
+pub struct GuildPlayerStates;
+
+impl TypeMapKey for GuildPlayerStates {
+    type Value = Arc<Mutex<HashMap<GuildId, Arc<GuildPlayerState>>>>;
+}
+
+/// The player state of the guild, created on first use.
+pub async fn guild_state(ctx: &Context, guild_id: GuildId) -> Arc<GuildPlayerState> {
+    let states = ctx
+        .data
+        .read()
+        .await
+        .get::<GuildPlayerStates>()
+        .expect("Guild player states placed in at initialisation.")
+        .clone();
+    let mut states = states.lock().await;
+    states.entry(guild_id).or_default().clone()
+}
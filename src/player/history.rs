@@ -0,0 +1,115 @@
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, DateTime};
+use mongodb::Collection;
+use serenity::async_trait;
+use serenity::model::prelude::{ChannelId, GuildId};
+use serenity::prelude::TypeMapKey;
+use songbird::{Event, EventContext, EventHandler};
+use tracing::{debug, error};
+
+use crate::dbmodels::history::HistoryEntry;
+use crate::mongo_conn::Db;
+use crate::player::{TrackRequester, TrackSkipped};
+
+/// The `_id` of the history document written when the track started.
+pub struct HistoryId;
+
+impl TypeMapKey for HistoryId {
+    type Value = ObjectId;
+}
+
+/// Writes a history entry the first time a track starts playing.
+pub struct HistoryStart {
+    pub db: Db,
+    pub guild_id: GuildId,
+    pub channel_id: Option<ChannelId>,
+}
+
+#[async_trait]
+impl EventHandler for HistoryStart {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let tracks = match ctx {
+            EventContext::Track(tracks) => tracks,
+            _ => return None,
+        };
+        let collection: Collection<HistoryEntry> = self.db.history();
+
+        for (_, track_handle) in tracks.iter() {
+            let mut typemap = track_handle.typemap().write().await;
+            // Play also fires when a paused track is resumed, only the first start is recorded.
+            if typemap.contains_key::<HistoryId>() {
+                continue;
+            }
+
+            let metadata = track_handle.metadata();
+            let entry = HistoryEntry {
+                id: None,
+                guild_ID: self.guild_id.0.to_string(),
+                channel_ID: self
+                    .channel_id
+                    .map(|id| id.0.to_string())
+                    .unwrap_or_else(|| "0".to_string()),
+                url: metadata.source_url.clone().unwrap_or_default(),
+                title: metadata.title.clone().unwrap_or_default(),
+                artist: metadata.artist.clone().or_else(|| metadata.channel.clone()),
+                requester_ID: typemap
+                    .get::<TrackRequester>()
+                    .map(|id| id.0.to_string())
+                    .unwrap_or_else(|| "0".to_string()),
+                started_at: DateTime::now(),
+                ended_at: None,
+                skipped: false,
+            };
+
+            match collection.insert_one(entry, None).await {
+                Ok(res) => {
+                    if let Some(id) = res.inserted_id.as_object_id() {
+                        debug!("Recorded history entry {}", id);
+                        typemap.insert::<HistoryId>(id);
+                    }
+                }
+                Err(err) => {
+                    error!("Failed to record history entry. {:?}", err);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Closes the history entry of a track once it ends or is skipped.
+pub struct HistoryEnd {
+    pub db: Db,
+}
+
+#[async_trait]
+impl EventHandler for HistoryEnd {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let tracks = match ctx {
+            EventContext::Track(tracks) => tracks,
+            _ => return None,
+        };
+        let collection: Collection<HistoryEntry> = self.db.history();
+
+        for (_, track_handle) in tracks.iter() {
+            let typemap = track_handle.typemap().read().await;
+            let history_id = match typemap.get::<HistoryId>() {
+                Some(id) => *id,
+                None => continue,
+            };
+            let skipped = typemap.get::<TrackSkipped>().copied().unwrap_or(false);
+
+            if let Err(err) = collection
+                .update_one(
+                    doc! {"_id": history_id},
+                    doc! {"$set": {"ended_at": DateTime::now(), "skipped": skipped}},
+                    None,
+                )
+                .await
+            {
+                error!("Failed to close history entry. {:?}", err);
+            }
+        }
+        None
+    }
+}
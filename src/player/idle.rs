@@ -0,0 +1,150 @@
+use mongodb::bson::doc;
+use serenity::model::prelude::{ChannelId, GuildId};
+use serenity::prelude::{Context, Mutex, TypeMapKey};
+use serenity::utils::Colour;
+use songbird::tracks::PlayMode;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info};
+
+use crate::dbmodels::guild::{default_idle_timeout, Guild};
+use crate::mongo_conn::Db;
+use crate::player::always_on::save_queue;
+use crate::player::announce::notice_channel;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The guilds that currently have an idle watcher running.
+pub struct IdleWatchers;
+
+impl TypeMapKey for IdleWatchers {
+    type Value = Arc<Mutex<HashSet<GuildId>>>;
+}
+
+/// Starts watching the guild's call, once nothing has played for the guild's idle timeout the
+/// bot posts a notice in the announce channel, or the voice channel it is in, and leaves. Guilds in 24/7 mode are never left, the
+/// watcher saves their queue instead. Does nothing if a watcher already runs.
+pub async fn watch(ctx: &Context, guild_id: GuildId, db: &Db) {
+    let watchers = match ctx.data.read().await.get::<IdleWatchers>() {
+        Some(watchers) => watchers.clone(),
+        None => {
+            error!("Idle watchers were not placed in at initialisation.");
+            return;
+        }
+    };
+    if !watchers.lock().await.insert(guild_id) {
+        debug!("Idle watcher already running for {}", guild_id);
+        return;
+    }
+
+    let ctx = ctx.clone();
+    let db = db.clone();
+    tokio::spawn(async move {
+        run(&ctx, guild_id, &db).await;
+        watchers.lock().await.remove(&guild_id);
+    });
+}
+
+async fn run(ctx: &Context, guild_id: GuildId, db: &Db) {
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+
+    let mut last_active = Instant::now();
+    let mut saved_urls: Vec<String> = vec![];
+    loop {
+        tokio::time::sleep(CHECK_INTERVAL).await;
+
+        let call_lock = match manager.get(guild_id) {
+            Some(call_lock) => call_lock,
+            None => {
+                debug!("Call in {} is gone, stopping the idle watcher", guild_id);
+                return;
+            }
+        };
+
+        let queued = call_lock.lock().await.queue().current_queue();
+        let guild_doc = guild_doc(db, guild_id).await;
+
+        // In 24/7 mode the bot never leaves, instead it keeps the queue saved for a restart.
+        if guild_doc.as_ref().map(|doc| doc.always_on).unwrap_or(false) {
+            let urls: Vec<String> = queued
+                .iter()
+                .filter_map(|track| track.metadata().source_url.clone())
+                .collect();
+            if urls != saved_urls {
+                save_queue(db, guild_id, &urls).await;
+                saved_urls = urls;
+            }
+            last_active = Instant::now();
+            continue;
+        }
+
+        let playing = match queued.first() {
+            Some(track_handle) => match track_handle.get_info().await {
+                Ok(state) => state.playing == PlayMode::Play,
+                Err(_) => false,
+            },
+            None => false,
+        };
+        if playing {
+            last_active = Instant::now();
+            continue;
+        }
+
+        let idle_timeout = guild_doc
+            .map(|doc| doc.idle_timeout)
+            .unwrap_or_else(default_idle_timeout);
+        if idle_timeout == 0 || last_active.elapsed() < Duration::from_secs(idle_timeout * 60) {
+            continue;
+        }
+
+        info!("Leaving {} after {} idle minutes", guild_id, idle_timeout);
+        // The bot may have been moved since the watcher started.
+        let voice_channel_id = call_lock
+            .lock()
+            .await
+            .current_channel()
+            .map(|channel| ChannelId(channel.0));
+        if let Some(channel_id) = notice_channel(db, guild_id, voice_channel_id).await {
+            let res = channel_id
+                .send_message(&ctx.http, |message| {
+                    message.embed(|embed| {
+                        embed
+                            .title("Leaving")
+                            .description(format!(
+                                "Nothing has played for {} minutes, see you next time!",
+                                idle_timeout
+                            ))
+                            .color(Colour::from_rgb(255, 165, 0))
+                    })
+                })
+                .await;
+            if let Err(err) = res {
+                error!("Could not send the idle notice. {}", err);
+            }
+        }
+
+        call_lock.lock().await.queue().stop();
+        if let Err(err) = manager.remove(guild_id).await {
+            error!("Failed to leave after idling. {:?}", err);
+        }
+        return;
+    }
+}
+
+async fn guild_doc(db: &Db, guild_id: GuildId) -> Option<Guild> {
+    match db
+        .guilds()
+        .find_one(doc! {"guild_ID": guild_id.0.to_string()}, None)
+        .await
+    {
+        Ok(guild_doc_opt) => guild_doc_opt,
+        Err(err) => {
+            error!("{:?}", err);
+            None
+        }
+    }
+}
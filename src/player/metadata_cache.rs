@@ -0,0 +1,180 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::mongo_conn::{cached_metadata, store_metadata, Db};
+use crate::player::sponsorblock::youtube_video_id;
+
+/// How long a video's metadata is used before it is refreshed.
+pub const VIDEO_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// How long a search keeps resolving to the same video.
+pub const SEARCH_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Playlists change the most, they are refreshed after an hour.
+pub const PLAYLIST_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The cache key of a video URL. YouTube links all become the same watch URL, other URLs lose
+/// their fragment and tracking parameters.
+pub fn url_key(url: &str) -> String {
+    if let Some(video_id) = youtube_video_id(url) {
+        return format!("url:https://www.youtube.com/watch?v={}", video_id);
+    }
+    match Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            let pairs: Vec<(String, String)> = parsed
+                .query_pairs()
+                .filter(|(key, _)| !key.starts_with("utm_") && key != "si" && key != "feature")
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            if pairs.is_empty() {
+                parsed.set_query(None);
+            } else {
+                parsed.query_pairs_mut().clear().extend_pairs(pairs);
+            }
+            format!("url:{}", parsed)
+        }
+        Err(_) => format!("url:{}", url),
+    }
+}
+
+/// The cache key of a search, the same for any casing and spacing of the query.
+pub fn search_key(query: &str) -> String {
+    format!("search:{}", normalize_query(query))
+}
+
+pub fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// The cache key of a playlist URL.
+pub fn playlist_key(url: &str) -> String {
+    format!("playlist:{}", url.trim())
+}
+
+/// Returns the cached yt-dlp output for the key, or runs `fetch` and caches what it prints.
+/// Entries older than the TTL are still used, but refreshed in the background for next time.
+pub async fn cached<F, Fut, E>(db: &Db, key: String, ttl: Duration, fetch: F) -> Result<String, E>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<String, E>> + Send + 'static,
+    E: Display + Send + 'static,
+{
+    let mut fetch = Some(fetch);
+    if let Some(json) = cached_entry(db, key.clone(), ttl, &mut fetch).await {
+        return Ok(json);
+    }
+
+    let fetch = fetch.expect("fetch is only taken to refresh a cached entry.");
+    let json = fetch().await?;
+    store(db, &key, &json).await;
+    Ok(json)
+}
+
+/// Like `cached`, but leaves fetching to the caller when nothing is cached. `fetch` is only
+/// taken to refresh a stale entry.
+pub async fn cached_entry<F, Fut, E>(
+    db: &Db,
+    key: String,
+    ttl: Duration,
+    fetch: &mut Option<F>,
+) -> Option<String>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<String, E>> + Send + 'static,
+    E: Display + Send + 'static,
+{
+    let entry = match cached_metadata(db, &key).await {
+        Ok(entry) => entry,
+        Err(err) => {
+            error!("{:?}", err);
+            None
+        }
+    };
+
+    let entry = entry?;
+    let age = Duration::from_millis(
+        (mongodb::bson::DateTime::now().timestamp_millis() - entry.fetched_at.timestamp_millis())
+            .max(0) as u64,
+    );
+    if age > ttl {
+        if let Some(fetch) = fetch.take() {
+            info!("Refreshing the stale metadata of {}.", key);
+            let db = db.clone();
+            tokio::spawn(async move {
+                match fetch().await {
+                    Ok(json) => store(&db, &key, &json).await,
+                    Err(err) => warn!("Failed to refresh the metadata of {}. {}", key, err),
+                }
+            });
+        }
+    }
+    Some(entry.json)
+}
+
+/// Saves yt-dlp output under the key, failures only mean it has to run again next time.
+pub async fn store(db: &Db, key: &str, json: &str) {
+    if let Err(err) = store_metadata(db, key, json).await {
+        error!("Failed to cache the metadata of {}. {:?}", key, err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WATCH_KEY: &str = "url:https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+
+    #[test]
+    fn youtube_links_share_one_key() {
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://youtube.com/watch?v=dQw4w9WgXcQ&list=PL123&index=2",
+            "https://m.youtube.com/watch?feature=share&v=dQw4w9WgXcQ",
+            "https://music.youtube.com/watch?v=dQw4w9WgXcQ&si=abc",
+            "https://youtu.be/dQw4w9WgXcQ?si=abc",
+            "https://www.youtube.com/shorts/dQw4w9WgXcQ",
+        ] {
+            assert_eq!(url_key(url), WATCH_KEY, "{}", url);
+        }
+    }
+
+    #[test]
+    fn other_urls_lose_tracking_parameters() {
+        assert_eq!(
+            url_key("https://soundcloud.com/artist/track?utm_source=x&si=y#t=30"),
+            "url:https://soundcloud.com/artist/track"
+        );
+        assert_eq!(
+            url_key("https://example.com/song.mp3?token=abc&utm_medium=share"),
+            "url:https://example.com/song.mp3?token=abc"
+        );
+    }
+
+    #[test]
+    fn searches_ignore_case_and_spacing() {
+        assert_eq!(
+            search_key("  Never Gonna\tGive  You Up "),
+            "search:never gonna give you up"
+        );
+        assert_eq!(
+            search_key("never gonna give you up"),
+            search_key("NEVER GONNA GIVE YOU UP")
+        );
+    }
+
+    #[test]
+    fn playlist_keys_are_trimmed() {
+        assert_eq!(
+            playlist_key(" https://www.youtube.com/playlist?list=PL123\n"),
+            "playlist:https://www.youtube.com/playlist?list=PL123"
+        );
+    }
+}
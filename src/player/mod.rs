@@ -0,0 +1,245 @@
+pub mod always_on;
+pub mod announce;
+pub mod cache;
+pub mod empty_channel;
+pub mod failures;
+pub mod governor;
+pub mod guild_state;
+pub mod history;
+pub mod idle;
+pub mod metadata_cache;
+pub mod prefetch;
+pub mod previous;
+pub mod reconnect;
+pub mod source;
+pub mod sponsorblock;
+pub mod ytdl;
+pub mod ytdl_error;
+
+use serenity::http::Http;
+use serenity::model::prelude::{ChannelId, GuildId, UserId};
+use serenity::prelude::{Context, Mutex, TypeMapKey};
+use songbird::error::JoinResult;
+use songbird::tracks::TrackHandle;
+use songbird::{create_player, Call, CoreEvent, Event, Songbird, TrackEvent};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::error;
+
+use crate::config::config;
+use crate::mongo_conn::Db;
+use crate::player::announce::TrackAnnouncer;
+use crate::player::cache::{audio_cache, CacheFiller, TrackCacheKey};
+use crate::player::failures::TrackFailureHandler;
+use crate::player::guild_state::{guild_state, GuildPlayerState};
+use crate::player::history::{HistoryEnd, HistoryStart};
+use crate::player::previous::PreviousRecorder;
+use crate::player::reconnect::{DriverDisconnectHandler, DriverReconnectHandler};
+use crate::player::source::{from_file, Resolved, TrackChapters, TrackStreamError};
+use crate::player::sponsorblock::{SegmentSkipper, CHECK_INTERVAL};
+
+/// The user who asked for a track, stored in the track's typemap.
+pub struct TrackRequester;
+
+impl TypeMapKey for TrackRequester {
+    type Value = UserId;
+}
+
+/// Set on a track's typemap right before it is skipped, so the end handlers can tell a skip
+/// apart from the track finishing on its own.
+pub struct TrackSkipped;
+
+impl TypeMapKey for TrackSkipped {
+    type Value = bool;
+}
+
+/// Joins the voice channel, calls that are new get the call event handlers registered.
+pub async fn join(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    db: &Db,
+) -> (Arc<Mutex<Call>>, JoinResult<()>) {
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+
+    let is_new = manager.get(guild_id).is_none();
+    let (call_lock, join_res) = manager.join(guild_id, channel_id).await;
+    if is_new {
+        let mut call = call_lock.lock().await;
+        let previous = previous::stacks(ctx).await;
+        let state = guild_state(ctx, guild_id).await;
+        register_call_events(
+            &mut call, &manager, &ctx.http, guild_id, db, &previous, &state,
+        );
+    }
+    (call_lock, join_res)
+}
+
+/// Adds the global handlers that keep the voice connection alive, deal with broken tracks,
+/// announce track starts and remember what played. These have to be added again whenever the
+/// global events are cleared.
+pub fn register_call_events(
+    call: &mut Call,
+    manager: &Arc<Songbird>,
+    http: &Arc<Http>,
+    guild_id: GuildId,
+    db: &Db,
+    previous: &Arc<Mutex<HashMap<GuildId, VecDeque<String>>>>,
+    state: &Arc<GuildPlayerState>,
+) {
+    call.add_global_event(
+        Event::Core(CoreEvent::DriverDisconnect),
+        DriverDisconnectHandler {
+            manager: manager.clone(),
+            http: http.clone(),
+            guild_id,
+            db: db.clone(),
+        },
+    );
+    call.add_global_event(
+        Event::Core(CoreEvent::DriverReconnect),
+        DriverReconnectHandler { guild_id },
+    );
+    call.add_global_event(
+        Event::Track(TrackEvent::End),
+        TrackFailureHandler {
+            manager: manager.clone(),
+            http: http.clone(),
+            guild_id,
+            db: db.clone(),
+            previous: previous.clone(),
+            state: state.clone(),
+        },
+    );
+    call.add_global_event(
+        Event::Track(TrackEvent::End),
+        PreviousRecorder {
+            previous: previous.clone(),
+            guild_id,
+        },
+    );
+    call.add_global_event(
+        Event::Track(TrackEvent::Play),
+        TrackAnnouncer {
+            http: http.clone(),
+            db: db.clone(),
+            guild_id,
+            state: state.clone(),
+        },
+    );
+    if config().features.sponsorblock {
+        call.add_global_event(
+            Event::Periodic(CHECK_INTERVAL, None),
+            SegmentSkipper {
+                manager: manager.clone(),
+                http: http.clone(),
+                guild_id,
+                db: db.clone(),
+            },
+        );
+    }
+}
+
+/// Queues the source on the call and attaches all of the per-track event handlers.
+pub async fn enqueue(
+    call: &mut Call,
+    mut source: Resolved,
+    volume: f32,
+    requester: UserId,
+    guild_id: GuildId,
+    db: &Db,
+) -> TrackHandle {
+    let channel_id = call.current_channel().map(|channel| ChannelId(channel.0));
+
+    // Plays from the disk cache when it has the track, otherwise remembers the key to fill it.
+    let mut cache_key = None;
+    if let (Some(audio_cache), Some(key)) = (audio_cache(), source.cache_key.take()) {
+        if cache::is_enabled(db, guild_id).await {
+            match audio_cache.lookup(&key).await {
+                Some(path) => {
+                    let metadata = *source.input.metadata.clone();
+                    match from_file(path, guild_id, metadata).await {
+                        Ok(input) => source.input = input,
+                        Err(err) => error!("Failed to play {} from the cache. {}", key, err),
+                    }
+                }
+                None => cache_key = Some(key),
+            }
+        }
+    }
+
+    let (mut track, track_handle) = create_player(source.input);
+    track.set_volume(volume);
+
+    {
+        let mut typemap = track_handle.typemap().write().await;
+        typemap.insert::<TrackRequester>(requester);
+        typemap.insert::<TrackChapters>(source.chapters);
+        typemap.insert::<TrackStreamError>(source.stream_error);
+        if let Some(key) = cache_key.clone() {
+            typemap.insert::<TrackCacheKey>(key);
+        }
+    }
+
+    if let Err(err) = track_handle.add_event(
+        Event::Track(TrackEvent::Play),
+        HistoryStart {
+            db: db.clone(),
+            guild_id,
+            channel_id,
+        },
+    ) {
+        error!("Failed to add the history start event. {}", err);
+    }
+    if let Err(err) =
+        track_handle.add_event(Event::Track(TrackEvent::End), HistoryEnd { db: db.clone() })
+    {
+        error!("Failed to add the history end event. {}", err);
+    }
+
+    if cache_key.is_some() {
+        if let Err(err) =
+            track_handle.add_event(Event::Track(TrackEvent::End), CacheFiller { guild_id })
+        {
+            error!("Failed to add the cache filler event. {}", err);
+        }
+    }
+
+    // Songbird pauses only tracks queued behind another, so a track queued into an empty queue
+    // would start in `PlayMode::Play` and never fire `TrackEvent::Play`. The history and the
+    // announcement hang off that event, so such a track is queued paused and started after.
+    let first = call.queue().is_empty();
+    track.pause();
+    call.enqueue(track);
+    if first {
+        if let Err(err) = track_handle.play() {
+            error!("Failed to start the track. {}", err);
+        }
+    }
+    track_handle
+}
+
+/// The track playing in the guild right now, if the bot is in a call there.
+pub async fn current_track(ctx: &Context, guild_id: Option<GuildId>) -> Option<TrackHandle> {
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+    let call_lock = manager.get(guild_id?)?;
+    let call = call_lock.lock().await;
+    call.queue().current()
+}
+
+/// Formats a track position or length as `m:ss`, or `h:mm:ss` for anything an hour or longer.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
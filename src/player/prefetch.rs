@@ -0,0 +1,240 @@
+use serenity::async_trait;
+use serenity::model::prelude::GuildId;
+use songbird::tracks::TrackHandle;
+use songbird::{Event, EventContext, EventHandler};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+use crate::mongo_conn::Db;
+use crate::player::source::{resolve, resolve_buffered, Resolved};
+
+/// How many upcoming entries are resolved ahead of time.
+pub const PREFETCH_COUNT: usize = 2;
+
+/// How long before the end of a track the upcoming entries are prefetched.
+const PREFETCH_LEAD: Duration = Duration::from_secs(20);
+
+struct Prefetched {
+    query: String,
+    source: Resolved,
+    /// Whether the stream was already started.
+    buffered: bool,
+}
+
+/// The entries of a playlist that weren't queued yet, and the ones among them that were already
+/// resolved. Only the first of those has its stream started, the others just have their metadata.
+/// A playlist that is still being listed keeps getting entries pushed until it is done loading.
+pub struct PendingEntries {
+    entries: Mutex<VecDeque<String>>,
+    // Only locked to take or add entries, never while one resolves.
+    prefetched: Mutex<VecDeque<Prefetched>>,
+    cancelled: AtomicBool,
+    loading: AtomicBool,
+    prefetching: AtomicBool,
+    arrived: Notify,
+    guild_id: GuildId,
+    db: Db,
+}
+
+impl PendingEntries {
+    pub fn new(entries: Vec<String>, guild_id: GuildId, db: &Db) -> Arc<PendingEntries> {
+        Arc::new(PendingEntries {
+            entries: Mutex::new(entries.into()),
+            prefetched: Mutex::new(VecDeque::new()),
+            cancelled: AtomicBool::new(false),
+            loading: AtomicBool::new(false),
+            prefetching: AtomicBool::new(false),
+            arrived: Notify::new(),
+            guild_id,
+            db: db.clone(),
+        })
+    }
+
+    /// Entries for a playlist that is still being listed, see `push` and `done_loading`.
+    pub fn loading(guild_id: GuildId, db: &Db) -> Arc<PendingEntries> {
+        let pending = PendingEntries::new(vec![], guild_id, db);
+        pending.loading.store(true, Ordering::SeqCst);
+        pending
+    }
+
+    /// Adds an entry that was just listed.
+    pub fn push(&self, query: String) {
+        if self.is_cancelled() {
+            return;
+        }
+        self.entries.lock().unwrap().push_back(query);
+        self.arrived.notify_waiters();
+    }
+
+    /// Marks the listing as complete, nothing more will be pushed.
+    pub fn done_loading(&self) {
+        self.loading.store(false, Ordering::SeqCst);
+        self.arrived.notify_waiters();
+    }
+
+    /// Whether there is anything left to queue, now or once the listing goes on.
+    pub fn has_more(&self) -> bool {
+        self.loading.load(Ordering::SeqCst)
+            || !self.entries.lock().unwrap().is_empty()
+            || !self.prefetched.lock().unwrap().is_empty()
+    }
+
+    /// Waits until an entry is available, or the listing ended without one.
+    pub async fn wait(&self) {
+        loop {
+            // Created before checking, so a push in between still wakes it.
+            let arrived = self.arrived.notified();
+            if !self.loading.load(Ordering::SeqCst)
+                || self.is_cancelled()
+                || !self.entries.lock().unwrap().is_empty()
+                || !self.prefetched.lock().unwrap().is_empty()
+            {
+                return;
+            }
+            arrived.await;
+        }
+    }
+
+    /// Takes the next entry, along with its source if it was prefetched. An entry that is still
+    /// being prefetched is taken as it is, the prefetch drops what it resolved.
+    pub fn next(&self) -> Option<(String, Option<Resolved>)> {
+        if let Some(prefetched) = self.prefetched.lock().unwrap().pop_front() {
+            return Some((prefetched.query, Some(prefetched.source)));
+        }
+        self.entries
+            .lock()
+            .unwrap()
+            .pop_front()
+            .map(|query| (query, None))
+    }
+
+    /// Drops the remaining entries, stopping any buffered streams.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+        self.prefetched.lock().unwrap().clear();
+    }
+
+    /// Stops prefetching for good, a prefetch that is still running drops what it resolved.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.loading.store(false, Ordering::SeqCst);
+        self.arrived.notify_waiters();
+        self.clear();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Prefetches the upcoming entries when the track gets close to its end. Tracks without a
+    /// known duration leave the next entry to be resolved once they end.
+    pub fn watch(self: &Arc<Self>, track_handle: &TrackHandle) {
+        let duration = match track_handle.metadata().duration {
+            Some(duration) => duration,
+            None => return,
+        };
+        if let Err(err) = track_handle.add_event(
+            Event::Delayed(duration.saturating_sub(PREFETCH_LEAD)),
+            Prefetcher {
+                pending: self.clone(),
+            },
+        ) {
+            error!("Failed to add the prefetch event. {}", err);
+        }
+    }
+
+    /// Resolves entries until `PREFETCH_COUNT` are ready, and starts the stream of the first.
+    /// Entries stay in place while they resolve, so one that is taken in the meantime is still
+    /// played in order, and the prefetched source is dropped.
+    async fn prefetch(&self) {
+        if self.prefetching.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        // Entries fetched as the second one only have metadata, which is cached by now.
+        let unbuffered = self
+            .prefetched
+            .lock()
+            .unwrap()
+            .front()
+            .filter(|front| !front.buffered)
+            .map(|front| front.query.clone());
+        if let Some(query) = unbuffered {
+            match resolve_buffered(&query, self.guild_id, &self.db).await {
+                Ok(source) => {
+                    let mut prefetched = self.prefetched.lock().unwrap();
+                    match prefetched.front_mut() {
+                        Some(front) if front.query == query && !front.buffered => {
+                            front.source = source;
+                            front.buffered = true;
+                        }
+                        _ => {}
+                    }
+                }
+                Err(err) => warn!("Failed to buffer {}. {}", query, err),
+            }
+        }
+
+        while !self.is_cancelled() {
+            let (query, buffered) = {
+                let prefetched = self.prefetched.lock().unwrap();
+                if prefetched.len() >= PREFETCH_COUNT {
+                    break;
+                }
+                match self.entries.lock().unwrap().front() {
+                    Some(query) => (query.clone(), prefetched.is_empty()),
+                    None => break,
+                }
+            };
+            let res = if buffered {
+                resolve_buffered(&query, self.guild_id, &self.db).await
+            } else {
+                resolve(&query, self.guild_id, &self.db).await
+            };
+            let source = match res {
+                Ok(source) => source,
+                Err(err) => {
+                    // Left for when it comes up, so the failure is reported then.
+                    warn!("Failed to prefetch {}. {}", query, err);
+                    break;
+                }
+            };
+
+            let mut prefetched = self.prefetched.lock().unwrap();
+            let mut entries = self.entries.lock().unwrap();
+            // Taken while it resolved, or the playlist was cancelled.
+            if self.is_cancelled() || entries.front() != Some(&query) {
+                break;
+            }
+            entries.pop_front();
+            info!("Prefetched {}.", query);
+            prefetched.push_back(Prefetched {
+                query,
+                source,
+                buffered,
+            });
+        }
+
+        self.prefetching.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Starts the prefetch near the end of a track.
+struct Prefetcher {
+    pending: Arc<PendingEntries>,
+}
+
+#[async_trait]
+impl EventHandler for Prefetcher {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        if !self.pending.is_cancelled() {
+            let pending = self.pending.clone();
+            tokio::spawn(async move { pending.prefetch().await });
+        }
+        None
+    }
+}
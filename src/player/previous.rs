@@ -0,0 +1,78 @@
+use serenity::async_trait;
+use serenity::model::prelude::GuildId;
+use serenity::prelude::{Context, Mutex, TypeMapKey};
+use songbird::{Event, EventContext, EventHandler};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How many previously played tracks are kept per guild.
+const MAX_PREVIOUS: usize = 20;
+
+/// The URLs of the tracks each guild played last, newest at the back.
+pub struct PreviousTracks;
+
+impl TypeMapKey for PreviousTracks {
+    type Value = Arc<Mutex<HashMap<GuildId, VecDeque<String>>>>;
+}
+
+/// The previously played tracks of all guilds.
+pub async fn stacks(ctx: &Context) -> Arc<Mutex<HashMap<GuildId, VecDeque<String>>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<PreviousTracks>()
+        .expect("Previous tracks placed in at initialisation.")
+        .clone()
+}
+
+/// Set on a track's typemap when it was brought back with `/back`. Such a track is not recorded
+/// again when it ends, so going back twice goes two tracks back.
+pub struct TrackReplayed;
+
+impl TypeMapKey for TrackReplayed {
+    type Value = bool;
+}
+
+/// Remembers every track that played once it ends or is skipped.
+pub struct PreviousRecorder {
+    pub previous: Arc<Mutex<HashMap<GuildId, VecDeque<String>>>>,
+    pub guild_id: GuildId,
+}
+
+#[async_trait]
+impl EventHandler for PreviousRecorder {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let tracks = match ctx {
+            EventContext::Track(tracks) => tracks,
+            _ => return None,
+        };
+
+        for (state, track_handle) in tracks.iter() {
+            // Stopping the queue ends tracks that never started.
+            if state.play_time == Duration::ZERO {
+                continue;
+            }
+            if track_handle
+                .typemap()
+                .read()
+                .await
+                .contains_key::<TrackReplayed>()
+            {
+                continue;
+            }
+            let url = match &track_handle.metadata().source_url {
+                Some(url) => url.clone(),
+                None => continue,
+            };
+
+            let mut previous = self.previous.lock().await;
+            let stack = previous.entry(self.guild_id).or_default();
+            stack.push_back(url);
+            if stack.len() > MAX_PREVIOUS {
+                stack.pop_front();
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,167 @@
+use serenity::async_trait;
+use serenity::http::Http;
+use serenity::model::prelude::{ChannelId, GuildId};
+use serenity::prelude::Context;
+use serenity::utils::Colour;
+use songbird::events::context_data::{DisconnectKind, DisconnectReason};
+use songbird::model::CloseCode;
+use songbird::{Event, EventContext, EventHandler, Songbird};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::mongo_conn::Db;
+use crate::player::always_on;
+use crate::player::announce::notice_channel;
+
+const MAX_ATTEMPTS: u32 = 5;
+const FIRST_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Rejoins the same channel with exponential backoff when the voice connection drops, the call
+/// and with it the queue and the current position are kept. Gives up after a few attempts,
+/// posts a notice in the announce channel, or the voice channel if there is none, and leaves.
+pub struct DriverDisconnectHandler {
+    pub manager: Arc<Songbird>,
+    pub http: Arc<Http>,
+    pub guild_id: GuildId,
+    pub db: Db,
+}
+
+#[async_trait]
+impl EventHandler for DriverDisconnectHandler {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let data = match ctx {
+            EventContext::DriverDisconnect(data) => data,
+            _ => return None,
+        };
+
+        // No reason means the disconnect was asked for, connect failures come from our own joins.
+        let reason = data.reason?;
+        if data.kind == DisconnectKind::Connect {
+            return None;
+        }
+        // Kicks and deleted channels close with this code, the voice state update handles them.
+        if reason == DisconnectReason::WsClosed(Some(CloseCode::Disconnected)) {
+            return None;
+        }
+        let channel_id = ChannelId(data.channel_id?.0);
+
+        warn!(
+            "Voice connection in {} dropped ({:?}), reconnecting",
+            self.guild_id, reason
+        );
+        let manager = self.manager.clone();
+        let http = self.http.clone();
+        let guild_id = self.guild_id;
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            reconnect(manager, http, guild_id, channel_id, db).await;
+        });
+        None
+    }
+}
+
+/// Logs when songbird manages to recover the connection on its own.
+pub struct DriverReconnectHandler {
+    pub guild_id: GuildId,
+}
+
+#[async_trait]
+impl EventHandler for DriverReconnectHandler {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        info!("Voice connection in {} reconnected", self.guild_id);
+        None
+    }
+}
+
+async fn reconnect(
+    manager: Arc<Songbird>,
+    http: Arc<Http>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    db: Db,
+) {
+    let mut backoff = FIRST_BACKOFF;
+    for attempt in 1..=MAX_ATTEMPTS {
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+
+        // The call was removed in the meantime, there is nothing to recover.
+        if manager.get(guild_id).is_none() {
+            return;
+        }
+
+        match manager.join(guild_id, channel_id).await.1 {
+            Ok(_) => {
+                info!(
+                    "Reconnected to {} in {} after {} attempts",
+                    channel_id, guild_id, attempt
+                );
+                return;
+            }
+            Err(err) => {
+                warn!(
+                    "Reconnect attempt {} in {} failed. {:?}",
+                    attempt, guild_id, err
+                );
+            }
+        }
+    }
+
+    error!("Giving up on reconnecting in {}", guild_id);
+    let notice_channel_id = notice_channel(&db, guild_id, Some(channel_id))
+        .await
+        .unwrap_or(channel_id);
+    let res = notice_channel_id
+        .send_message(&http, |message| {
+            message.embed(|embed| {
+                embed
+                    .title("Connection Lost")
+                    .description(
+                        "The voice connection dropped and could not be recovered. \
+                         Use /play to start again.",
+                    )
+                    .color(Colour::from_rgb(255, 0, 0))
+            })
+        })
+        .await;
+    if let Err(err) = res {
+        error!("Could not send the reconnect notice. {}", err);
+    }
+    if let Err(err) = manager.remove(guild_id).await {
+        error!("Failed to clean up the call. {:?}", err);
+    }
+}
+
+/// Called when the bot's own voice state loses its channel. A call that is still around was not
+/// left on purpose, so the bot was kicked or the channel went away. Guilds in 24/7 mode rejoin,
+/// everything else gets the call cleaned up.
+pub async fn dropped(ctx: &Context, guild_id: GuildId, db: &Db) {
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+    // Leaving on purpose removes the call.
+    if manager.get(guild_id).is_none() {
+        return;
+    }
+
+    if always_on::rejoin_if_enabled(ctx, guild_id, db).await {
+        return;
+    }
+
+    info!(
+        "Disconnected from voice in {} by someone else, cleaning up",
+        guild_id
+    );
+    if let Err(err) = manager.remove(guild_id).await {
+        error!("Failed to clean up the call. {:?}", err);
+    }
+}
+
+/// Called when the bot's own voice state moves to another channel, e.g. a mod dragged it. 24/7
+/// mode follows the bot to the new channel.
+pub async fn moved(guild_id: GuildId, channel_id: ChannelId, db: &Db) {
+    info!("Moved to {} in {}", channel_id, guild_id);
+    always_on::update_channel(db, guild_id, channel_id).await;
+}
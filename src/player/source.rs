@@ -0,0 +1,390 @@
+use serde_json::Value;
+use serenity::async_trait;
+use serenity::model::prelude::GuildId;
+use serenity::prelude::TypeMapKey;
+use songbird::input::error::{Error, Result};
+use songbird::input::restartable::Restart;
+use songbird::input::{Codec, Container, Input, Metadata, Restartable};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use url::Url;
+
+use crate::mongo_conn::Db;
+use crate::player::governor::{governed, governor, AudioPermit};
+use crate::player::metadata_cache::{cached, search_key, store, url_key, SEARCH_TTL, VIDEO_TTL};
+use crate::player::ytdl::extractor;
+use crate::player::ytdl_error::YtdlError;
+
+/// A chapter of a video, as listed by yt-dlp.
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    pub title: String,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// The chapters of the track, stored in the track's typemap. Empty for videos without any.
+pub struct TrackChapters;
+
+impl TypeMapKey for TrackChapters {
+    type Value = Vec<Chapter>;
+}
+
+/// The error a streaming yt-dlp printed, set once its stderr closes. Shared between the
+/// restarter that runs yt-dlp and the track, so a failed track can say why it failed.
+#[derive(Clone, Default)]
+pub struct StreamError(Arc<Mutex<Option<YtdlError>>>);
+
+impl StreamError {
+    pub fn get(&self) -> Option<YtdlError> {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// The stream error of the track, stored in the track's typemap.
+pub struct TrackStreamError;
+
+impl TypeMapKey for TrackStreamError {
+    type Value = StreamError;
+}
+
+/// A resolved query, ready to be queued.
+pub struct Resolved {
+    pub input: Input,
+    pub chapters: Vec<Chapter>,
+    /// Names the audio in the disk cache, the extractor and video ID.
+    pub cache_key: Option<String>,
+    pub stream_error: StreamError,
+}
+
+/// Turns a URL or a search query into a seekable input. The metadata comes from the metadata
+/// cache, or from yt-dlp when it isn't cached yet. The stream itself is only started when the
+/// track comes up in the queue.
+pub async fn resolve(query: &str, guild_id: GuildId, db: &Db) -> Result<Resolved> {
+    resolve_with(query, guild_id, db, true).await
+}
+
+/// Like `resolve`, but starts the stream right away so the track plays without a delay. The
+/// processes are stopped when the input is dropped.
+pub async fn resolve_buffered(query: &str, guild_id: GuildId, db: &Db) -> Result<Resolved> {
+    resolve_with(query, guild_id, db, false).await
+}
+
+async fn resolve_with(query: &str, guild_id: GuildId, db: &Db, lazy: bool) -> Result<Resolved> {
+    let target = match Url::parse(query) {
+        Ok(_) => query.to_string(),
+        Err(_) => format!("ytsearch1:{}", query),
+    };
+
+    let fetch_target = target.clone();
+    let json = match Url::parse(query) {
+        Ok(_) => {
+            cached(db, url_key(query), VIDEO_TTL, move || async move {
+                ytdl_info(&fetch_target).await
+            })
+            .await?
+        }
+        Err(_) => {
+            let fetch_client = db.clone();
+            // The video a search found is cached under its URL as well.
+            cached(db, search_key(query), SEARCH_TTL, move || async move {
+                let json = ytdl_info(&fetch_target).await?;
+                if let Some(url) = serde_json::from_str::<Value>(&json)
+                    .ok()
+                    .and_then(|info| info.get("webpage_url")?.as_str().map(url_key))
+                {
+                    store(&fetch_client, &url, &json).await;
+                }
+                Ok::<String, Error>(json)
+            })
+            .await?
+        }
+    };
+
+    let info: Value = serde_json::from_str(&json).map_err(|error| Error::Json {
+        error,
+        parsed_text: json.clone(),
+    })?;
+    let chapters = chapters_from_ytdl_output(&info);
+    let cache_key = cache_key_from_ytdl_output(&info);
+    let mut metadata = Metadata::from_ytdl_output(info);
+    // ffmpeg always hands over 48kHz stereo, the lazy input takes its layout from here.
+    metadata.channels = Some(2);
+    metadata.sample_rate = Some(48000);
+
+    let url = metadata.source_url.clone().unwrap_or(target);
+    let stream_error = StreamError::default();
+    let restartable = Restartable::new(
+        YtdlRestarter {
+            url,
+            guild_id,
+            metadata: Some(metadata),
+            started: false,
+            stream_error: stream_error.clone(),
+        },
+        lazy,
+    )
+    .await?;
+
+    Ok(Resolved {
+        input: restartable.into(),
+        chapters,
+        cache_key,
+        stream_error,
+    })
+}
+
+/// Builds a seekable input for audio in the disk cache, keeping the metadata of the original.
+pub async fn from_file(path: PathBuf, guild_id: GuildId, metadata: Metadata) -> Result<Input> {
+    let restartable = Restartable::new(
+        FileRestarter {
+            path,
+            guild_id,
+            metadata: Some(metadata),
+            started: false,
+        },
+        true,
+    )
+    .await?;
+    Ok(restartable.into())
+}
+
+/// The fields of yt-dlp's JSON output the bot reads, everything else is dropped before caching.
+const KEPT_FIELDS: [&str; 14] = [
+    "id",
+    "extractor_key",
+    "title",
+    "track",
+    "artist",
+    "uploader",
+    "channel",
+    "upload_date",
+    "release_date",
+    "duration",
+    "webpage_url",
+    "thumbnail",
+    "chapters",
+    "is_live",
+];
+
+/// Runs yt-dlp for the JSON description of a single video, trimmed to the kept fields.
+async fn ytdl_info(target: &str) -> Result<String> {
+    let extractor = extractor();
+    let output = extractor
+        .run(
+            &[
+                "-j",
+                "-f",
+                &extractor.config.format,
+                "--no-playlist",
+                target,
+            ],
+            extractor.config.timeout(),
+        )
+        .await?;
+
+    let line = output
+        .stdout
+        .split(|byte| *byte == b'\n')
+        .next()
+        .unwrap_or_default();
+    if !output.status.success() || line.is_empty() {
+        return Err(Error::YouTubeDlRun(output));
+    }
+    let info: Value = serde_json::from_slice(line).map_err(|error| Error::Json {
+        error,
+        parsed_text: String::from_utf8_lossy(line).to_string(),
+    })?;
+
+    let trimmed: serde_json::Map<String, Value> = KEPT_FIELDS
+        .iter()
+        .filter_map(|field| Some((field.to_string(), info.get(field)?.clone())))
+        .collect();
+    Ok(Value::Object(trimmed).to_string())
+}
+
+/// Reads the `chapters` list of yt-dlp's JSON output.
+pub fn chapters_from_ytdl_output(value: &Value) -> Vec<Chapter> {
+    let chapters = match value.get("chapters").and_then(Value::as_array) {
+        Some(chapters) => chapters,
+        None => return vec![],
+    };
+
+    chapters
+        .iter()
+        .filter_map(|chapter| {
+            let start = chapter.get("start_time")?.as_f64()?;
+            let end = chapter.get("end_time")?.as_f64()?;
+            Some(Chapter {
+                title: chapter
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                start: Duration::from_secs_f64(start.max(0.0)),
+                end: Duration::from_secs_f64(end.max(start).max(0.0)),
+            })
+        })
+        .collect()
+}
+
+/// Reads the extractor and ID of yt-dlp's JSON output into a file name safe key.
+fn cache_key_from_ytdl_output(value: &Value) -> Option<String> {
+    let extractor = value.get("extractor_key").and_then(Value::as_str)?;
+    let id = value.get("id").and_then(Value::as_str)?;
+    let key: String = format!("{}-{}", extractor, id)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    Some(key.to_lowercase())
+}
+
+/// A slot for a new pipeline. Seeks replace the pipeline of a playing track, they don't queue.
+async fn audio_permit(guild_id: GuildId, seek: bool) -> AudioPermit {
+    if seek {
+        governor().acquire_now(guild_id)
+    } else {
+        governor().acquire(guild_id).await
+    }
+}
+
+/// The `-ss` arguments for ffmpeg to start at the position, none for the start of the track.
+fn start_args(time: Option<Duration>) -> Vec<String> {
+    match time {
+        Some(time) if !time.is_zero() => {
+            vec!["-ss".to_string(), format!("{:.3}", time.as_secs_f64())]
+        }
+        _ => vec![],
+    }
+}
+
+/// Starts yt-dlp piped into ffmpeg, from the given position on a seek.
+struct YtdlRestarter {
+    url: String,
+    guild_id: GuildId,
+    metadata: Option<Metadata>,
+    /// Set once a pipeline was started. Songbird starts a lazy input with a seek to zero, so only
+    /// restarts after that are seeks of a track that is already playing.
+    started: bool,
+    stream_error: StreamError,
+}
+
+#[async_trait]
+impl Restart for YtdlRestarter {
+    async fn call_restart(&mut self, time: Option<Duration>) -> Result<Input> {
+        let permit = audio_permit(self.guild_id, self.started).await;
+        let mut youtube_dl = extractor()
+            .stream_command()
+            .args(["-R", "infinite", "--no-playlist", &self.url, "-o", "-"])
+            .stdin(Stdio::null())
+            .stderr(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdout = youtube_dl.stdout.take().ok_or(Error::Stdout)?;
+        if let Some(stderr) = youtube_dl.stderr.take() {
+            let stream_error = self.stream_error.clone();
+            // Blocks until yt-dlp exits, which is when it has said all it will.
+            std::thread::spawn(move || {
+                if let Some(err) = YtdlError::from_stream(stderr) {
+                    *stream_error.0.lock().unwrap() = Some(err);
+                }
+            });
+        }
+
+        let ffmpeg = std::process::Command::new("ffmpeg")
+            .args(start_args(time))
+            .args([
+                "-i",
+                "-",
+                "-f",
+                "s16le",
+                "-ac",
+                "2",
+                "-ar",
+                "48000",
+                "-acodec",
+                "pcm_f32le",
+                "-",
+            ])
+            .stdin(stdout)
+            .stderr(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        self.started = true;
+
+        Ok(Input::new(
+            true,
+            governed(vec![youtube_dl, ffmpeg], permit),
+            Codec::FloatPcm,
+            Container::Raw,
+            self.metadata.clone(),
+        ))
+    }
+
+    async fn lazy_init(&mut self) -> Result<(Option<Metadata>, Codec, Container)> {
+        Ok((self.metadata.clone(), Codec::FloatPcm, Container::Raw))
+    }
+}
+
+/// Starts ffmpeg on a file of the disk cache, from the given position on a seek.
+struct FileRestarter {
+    path: PathBuf,
+    guild_id: GuildId,
+    metadata: Option<Metadata>,
+    /// Set once a pipeline was started, see `YtdlRestarter`.
+    started: bool,
+}
+
+#[async_trait]
+impl Restart for FileRestarter {
+    async fn call_restart(&mut self, time: Option<Duration>) -> Result<Input> {
+        let permit = audio_permit(self.guild_id, self.started).await;
+        let ffmpeg = std::process::Command::new("ffmpeg")
+            .args(start_args(time))
+            .arg("-i")
+            .arg(&self.path)
+            .args([
+                "-f",
+                "s16le",
+                "-ac",
+                "2",
+                "-ar",
+                "48000",
+                "-acodec",
+                "pcm_f32le",
+                "-",
+            ])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        self.started = true;
+
+        Ok(Input::new(
+            true,
+            governed(vec![ffmpeg], permit),
+            Codec::FloatPcm,
+            Container::Raw,
+            self.metadata.clone(),
+        ))
+    }
+
+    async fn lazy_init(&mut self) -> Result<(Option<Metadata>, Codec, Container)> {
+        Ok((self.metadata.clone(), Codec::FloatPcm, Container::Raw))
+    }
+}
+
+/// The index of the chapter playing at the given position.
+pub fn chapter_at(chapters: &[Chapter], position: Duration) -> Option<usize> {
+    chapters
+        .iter()
+        .rposition(|chapter| chapter.start <= position)
+}
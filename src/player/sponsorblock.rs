@@ -0,0 +1,347 @@
+use mongodb::bson::doc;
+use serde::Deserialize;
+use serenity::async_trait;
+use serenity::http::Http;
+use serenity::model::prelude::{ChannelId, GuildId};
+use serenity::prelude::TypeMapKey;
+use songbird::tracks::{PlayMode, TrackHandle};
+use songbird::{Event, EventContext, EventHandler, Songbird};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+use url::Url;
+
+use crate::config::config;
+use crate::mongo_conn::Db;
+use crate::player::announce::notice_channel;
+use crate::player::format_duration;
+
+/// The categories the bot knows how to skip, in the order they are shown.
+pub const CATEGORIES: [&str; 4] = ["sponsor", "intro", "outro", "selfpromo"];
+
+/// How often the position of the playing track is checked against its segments.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Segments that end this close to the end of the track stop it instead of seeking.
+const END_MARGIN: Duration = Duration::from_secs(1);
+
+/// A part of a video to skip, as submitted to SponsorBlock.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub category: String,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// The segments of the track that weren't skipped yet, stored in the track's typemap once they
+/// were looked up. Empty when the track has none, or the guild doesn't skip anything.
+pub struct TrackSegments;
+
+impl TypeMapKey for TrackSegments {
+    type Value = Vec<Segment>;
+}
+
+#[derive(Deserialize)]
+struct ApiSegment {
+    category: String,
+    segment: [f64; 2],
+}
+
+/// Skips over the SponsorBlock segments of the playing track, looking them up the first time the
+/// track is seen playing.
+pub struct SegmentSkipper {
+    pub manager: Arc<Songbird>,
+    pub http: Arc<Http>,
+    pub guild_id: GuildId,
+    pub db: Db,
+}
+
+#[async_trait]
+impl EventHandler for SegmentSkipper {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let tracks = match ctx {
+            EventContext::Track(tracks) => tracks,
+            _ => return None,
+        };
+
+        for (track_state, track_handle) in tracks.iter() {
+            if track_state.playing != PlayMode::Play {
+                continue;
+            }
+
+            let segment = {
+                let mut typemap = track_handle.typemap().write().await;
+                match typemap.get_mut::<TrackSegments>() {
+                    // Each segment is only skipped once, the position can lag behind for a few
+                    // checks while the seek restarts the stream.
+                    Some(segments) => segments
+                        .iter()
+                        .position(|segment| {
+                            segment.start <= track_state.position
+                                && track_state.position < segment.end
+                        })
+                        .map(|index| segments.remove(index)),
+                    None => {
+                        // Marked as looked up right away, so the next check doesn't start
+                        // another request while this one is still out.
+                        typemap.insert::<TrackSegments>(vec![]);
+                        tokio::spawn(look_up(
+                            (*track_handle).clone(),
+                            self.guild_id,
+                            self.db.clone(),
+                        ));
+                        None
+                    }
+                }
+            };
+
+            if let Some(segment) = segment {
+                self.skip(track_handle, &segment).await;
+            }
+        }
+
+        None
+    }
+}
+
+impl SegmentSkipper {
+    async fn skip(&self, track_handle: &TrackHandle, segment: &Segment) {
+        let near_end = track_handle
+            .metadata()
+            .duration
+            .is_some_and(|duration| segment.end + END_MARGIN >= duration);
+        let res = if near_end {
+            track_handle.stop()
+        } else {
+            track_handle.seek_time(segment.end)
+        };
+        if let Err(err) = res {
+            warn!("Failed to skip a {} segment. {}", segment.category, err);
+            return;
+        }
+
+        let voice_channel_id = match self.manager.get(self.guild_id) {
+            Some(call_lock) => call_lock
+                .lock()
+                .await
+                .current_channel()
+                .map(|channel| ChannelId(channel.0)),
+            None => None,
+        };
+        let channel_id = notice_channel(&self.db, self.guild_id, voice_channel_id).await;
+        if let Some(channel_id) = channel_id {
+            let res = channel_id
+                .say(
+                    &self.http,
+                    format!(
+                        "Skipped a {} segment ({} - {}).",
+                        segment.category,
+                        format_duration(segment.start),
+                        format_duration(segment.end)
+                    ),
+                )
+                .await;
+            if let Err(err) = res {
+                error!("Failed to post the skip note. {}", err);
+            }
+        }
+    }
+}
+
+/// Looks up the segments of a track for the categories its guild skips.
+async fn look_up(track_handle: TrackHandle, guild_id: GuildId, db: Db) {
+    let video_id = match track_handle
+        .metadata()
+        .source_url
+        .as_deref()
+        .and_then(youtube_video_id)
+    {
+        Some(video_id) => video_id,
+        None => return,
+    };
+
+    let categories = match db
+        .guilds()
+        .find_one(doc! {"guild_ID": guild_id.0.to_string()}, None)
+        .await
+    {
+        Ok(Some(guild_doc)) => guild_doc.sponsorblock_categories,
+        Ok(None) => return,
+        Err(err) => {
+            error!("{:?}", err);
+            return;
+        }
+    };
+    if categories.is_empty() {
+        return;
+    }
+
+    match fetch_segments(&config().sponsorblock.api_url, &video_id, &categories).await {
+        Ok(segments) => {
+            track_handle
+                .typemap()
+                .write()
+                .await
+                .insert::<TrackSegments>(segments);
+        }
+        Err(err) => warn!("Failed to get the segments of {}. {}", video_id, err),
+    }
+}
+
+/// Asks a SponsorBlock-compatible API at `api_url`, `sponsorblock.api_url` outside of tests, for
+/// the segments of a video.
+pub async fn fetch_segments(
+    api_url: &str,
+    video_id: &str,
+    categories: &[String],
+) -> reqwest::Result<Vec<Segment>> {
+    let query = serde_json::to_string(categories).unwrap_or_default();
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/api/skipSegments",
+            api_url.trim_end_matches('/')
+        ))
+        .query(&[("videoID", video_id), ("categories", &query)])
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await?;
+    // The API answers 404 when a video has no segments.
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(vec![]);
+    }
+
+    let segments: Vec<ApiSegment> = response.error_for_status()?.json().await?;
+    // Only what was asked for is skipped, whatever else the API sends back.
+    Ok(segments
+        .into_iter()
+        .filter(|segment| categories.contains(&segment.category))
+        .filter(|segment| segment.segment[0] >= 0.0 && segment.segment[1] > segment.segment[0])
+        .map(|segment| Segment {
+            category: segment.category,
+            start: Duration::from_secs_f64(segment.segment[0]),
+            end: Duration::from_secs_f64(segment.segment[1]),
+        })
+        .collect())
+}
+
+/// The id of a YouTube video URL, None for anything else.
+pub fn youtube_video_id(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?.trim_start_matches("www.");
+    match host {
+        "youtu.be" => url.path_segments()?.next().map(str::to_string),
+        "youtube.com" | "m.youtube.com" | "music.youtube.com" => {
+            if let Some(id) = url.path().strip_prefix("/shorts/") {
+                return Some(id.to_string());
+            }
+            url.query_pairs()
+                .find(|(key, _)| key == "v")
+                .map(|(_, id)| id.to_string())
+        }
+        _ => None,
+    }
+    .filter(|id| !id.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// A local stand-in for the API that answers one request, returning its URL and a handle
+    /// that resolves to the request line it got.
+    async fn stand_in(status: &'static str, body: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let read = socket.read(&mut request).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..read]).to_string();
+            request.lines().next().unwrap_or_default().to_string()
+        });
+        (url, handle)
+    }
+
+    fn categories(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn missing_video_has_no_segments() {
+        let (url, request) = stand_in("404 Not Found", "Not Found").await;
+        let segments = fetch_segments(&url, "abc", &categories(&["sponsor"]))
+            .await
+            .unwrap();
+        assert!(segments.is_empty());
+        assert!(request
+            .await
+            .unwrap()
+            .starts_with("GET /api/skipSegments?videoID=abc&"));
+    }
+
+    #[tokio::test]
+    async fn only_the_given_categories_are_kept() {
+        let body = r#"[
+            {"category": "intro", "segment": [0.0, 12.5]},
+            {"category": "sponsor", "segment": [20.0, 50.0]}
+        ]"#;
+        let (url, request) = stand_in("200 OK", body).await;
+        let segments = fetch_segments(&url, "abc", &categories(&["intro", "outro"]))
+            .await
+            .unwrap();
+
+        let request = request.await.unwrap();
+        let query = Url::parse(&format!(
+            "http://localhost{}",
+            request.split(' ').nth(1).unwrap()
+        ))
+        .unwrap();
+        let asked: Vec<String> = query
+            .query_pairs()
+            .filter(|(key, _)| key == "categories")
+            .map(|(_, value)| value.to_string())
+            .collect();
+        assert_eq!(asked, [r#"["intro","outro"]"#]);
+
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].category, "intro");
+        assert_eq!(segments[0].start, Duration::ZERO);
+        assert_eq!(segments[0].end, Duration::from_millis(12_500));
+    }
+
+    #[tokio::test]
+    async fn invalid_segments_are_dropped() {
+        let body = r#"[
+            {"category": "sponsor", "segment": [-1.0, 5.0]},
+            {"category": "sponsor", "segment": [30.0, 30.0]},
+            {"category": "sponsor", "segment": [40.0, 35.0]},
+            {"category": "sponsor", "segment": [60.0, 75.0]}
+        ]"#;
+        let (url, _request) = stand_in("200 OK", body).await;
+        let segments = fetch_segments(&url, "abc", &categories(&["sponsor"]))
+            .await
+            .unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].start, Duration::from_secs(60));
+        assert_eq!(segments[0].end, Duration::from_secs(75));
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_reported() {
+        let (url, _request) = stand_in("500 Internal Server Error", "").await;
+        assert!(fetch_segments(&url, "abc", &categories(&["sponsor"]))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,272 @@
+use serde::Deserialize;
+use std::process::{ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdout, Command as TokioCommand};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::config::config;
+
+/// The format yt-dlp picks when `YTDL_FORMAT` isn't set.
+const DEFAULT_FORMAT: &str = "webm[abr>0]/bestaudio/best";
+
+/// How yt-dlp is run, the `[ytdl]` section of the config.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct YtdlConfig {
+    /// `YTDL_PATH`, the binary to run.
+    pub path: String,
+    /// `YTDL_FORMAT`, the format selection for audio.
+    pub format: String,
+    /// `YTDL_COOKIES`, a cookies file for sites that need a login.
+    pub cookies: Option<String>,
+    /// `YTDL_PROXY`, passed on to `--proxy`.
+    pub proxy: Option<String>,
+    /// `YTDL_EXTRA_ARGS`, whitespace separated arguments added to every run.
+    pub extra_args: Vec<String>,
+    /// `YTDL_MAX_CONCURRENT`, how many lookups may run at once.
+    pub max_concurrent: usize,
+    /// `YTDL_TIMEOUT_SECS`, how long a single video lookup may take.
+    pub timeout_secs: u64,
+    /// `YTDL_PLAYLIST_TIMEOUT_SECS`, how long listing a playlist may take. While the entries are
+    /// streamed, this is how long yt-dlp may go without printing one.
+    pub playlist_timeout_secs: u64,
+}
+
+impl Default for YtdlConfig {
+    fn default() -> Self {
+        YtdlConfig {
+            path: "yt-dlp".to_string(),
+            format: DEFAULT_FORMAT.to_string(),
+            cookies: None,
+            proxy: None,
+            extra_args: vec![],
+            max_concurrent: 4,
+            timeout_secs: 30,
+            playlist_timeout_secs: 120,
+        }
+    }
+}
+
+impl YtdlConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    pub fn playlist_timeout(&self) -> Duration {
+        Duration::from_secs(self.playlist_timeout_secs)
+    }
+}
+
+/// Every yt-dlp process of the bot is started here. Lookups wait for a free slot and are killed
+/// when they take too long, streams only share the binary and arguments.
+pub struct Extractor {
+    pub config: &'static YtdlConfig,
+    permits: Semaphore,
+    waiting: AtomicUsize,
+}
+
+/// The extractor, set up from the config on first use.
+pub fn extractor() -> &'static Extractor {
+    static EXTRACTOR: OnceLock<Extractor> = OnceLock::new();
+    EXTRACTOR.get_or_init(|| {
+        let config = &config().ytdl;
+        info!(
+            "Running {} with up to {} concurrent lookups.",
+            config.path, config.max_concurrent
+        );
+        Extractor {
+            permits: Semaphore::new(config.max_concurrent),
+            waiting: AtomicUsize::new(0),
+            config,
+        }
+    })
+}
+
+impl Extractor {
+    /// The arguments every run gets, before the ones of the call.
+    fn common_args(&self) -> Vec<String> {
+        let mut args = vec!["--ignore-config".to_string(), "--no-warnings".to_string()];
+        if let Some(cookies) = &self.config.cookies {
+            args.push("--cookies".to_string());
+            args.push(cookies.clone());
+        }
+        if let Some(proxy) = &self.config.proxy {
+            args.push("--proxy".to_string());
+            args.push(proxy.clone());
+        }
+        args.extend(self.config.extra_args.iter().cloned());
+        args
+    }
+
+    /// Waits for a free lookup slot, returning it with how long that took.
+    async fn slot(&self) -> std::io::Result<(SemaphorePermit<'_>, Duration)> {
+        let queued_at = Instant::now();
+        let depth = self.waiting.fetch_add(1, Ordering::Relaxed) + 1;
+        let waiting = Waiting(&self.waiting);
+        debug!(queue_depth = depth, "Waiting for a yt-dlp slot.");
+        let permit = self.permits.acquire().await;
+        drop(waiting);
+        Ok((permit.map_err(std::io::Error::other)?, queued_at.elapsed()))
+    }
+
+    fn lookup_command(&self, args: &[&str]) -> TokioCommand {
+        let mut command = TokioCommand::new(&self.config.path);
+        command
+            .args(self.common_args())
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        command
+    }
+
+    /// Like `run`, but hands out stdout line by line while yt-dlp is still running. `timeout` is
+    /// an idle timeout here, it starts over with every line. The slot is held until the lines
+    /// are dropped.
+    pub async fn run_lines(
+        &'static self,
+        args: &[&str],
+        timeout: Duration,
+    ) -> std::io::Result<YtdlLines> {
+        let (permit, waited) = self.slot().await?;
+        debug!(
+            waited_ms = waited.as_millis() as u64,
+            "Streaming yt-dlp output."
+        );
+
+        let mut child = self.lookup_command(args).spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| std::io::Error::other("yt-dlp has no stdout"))?;
+        let mut stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| std::io::Error::other("yt-dlp has no stderr"))?;
+        // Read alongside stdout, a full stderr pipe would stall yt-dlp.
+        let stderr = tokio::spawn(async move {
+            let mut buf = vec![];
+            let _res = stderr.read_to_end(&mut buf).await;
+            buf
+        });
+
+        Ok(YtdlLines {
+            child,
+            lines: BufReader::new(stdout).lines(),
+            stderr,
+            timeout,
+            _permit: permit,
+        })
+    }
+
+    /// A yt-dlp command for streaming audio, with the configured format. Streams run as long as
+    /// the track plays, so they are neither limited nor timed out here.
+    pub fn stream_command(&self) -> std::process::Command {
+        let mut command = std::process::Command::new(&self.config.path);
+        command
+            .args(self.common_args())
+            .args(["-f", &self.config.format]);
+        command
+    }
+
+    /// Runs a lookup once a slot is free, capturing its output. The process is killed if it
+    /// runs past `timeout`, which surfaces as a `TimedOut` error.
+    pub async fn run(&self, args: &[&str], timeout: Duration) -> std::io::Result<Output> {
+        let (_permit, waited) = self.slot().await?;
+
+        let started_at = Instant::now();
+        let child = self.lookup_command(args).spawn()?;
+        let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+            Ok(output) => output?,
+            Err(_) => {
+                warn!(
+                    waited_ms = waited.as_millis() as u64,
+                    "yt-dlp was killed after {}s.",
+                    timeout.as_secs()
+                );
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("yt-dlp took longer than {}s", timeout.as_secs()),
+                ));
+            }
+        };
+
+        debug!(
+            waited_ms = waited.as_millis() as u64,
+            ran_ms = started_at.elapsed().as_millis() as u64,
+            status = %output.status,
+            "yt-dlp finished."
+        );
+        if !output.status.success() {
+            warn!(
+                "yt-dlp exited with {}. {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(output)
+    }
+}
+
+/// Counts a lookup as waiting for a slot until it is dropped, also when the lookup is cancelled
+/// while it waits.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The output of a running lookup, see `Extractor::run_lines`.
+pub struct YtdlLines {
+    child: Child,
+    lines: Lines<BufReader<ChildStdout>>,
+    stderr: JoinHandle<Vec<u8>>,
+    timeout: Duration,
+    _permit: SemaphorePermit<'static>,
+}
+
+impl YtdlLines {
+    /// The next line yt-dlp printed, `None` once it closed stdout. Fails with `TimedOut` when
+    /// yt-dlp prints nothing for too long, the process is killed when the lines are dropped.
+    pub async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        match tokio::time::timeout(self.timeout, self.lines.next_line()).await {
+            Ok(line) => line,
+            Err(_) => {
+                warn!(
+                    "yt-dlp was killed after {}s without output.",
+                    self.timeout.as_secs()
+                );
+                Err(self.timed_out())
+            }
+        }
+    }
+
+    /// Waits for yt-dlp to exit, returning its status and everything it printed to stderr.
+    pub async fn finish(mut self) -> std::io::Result<(ExitStatus, String)> {
+        let status = match tokio::time::timeout(self.timeout, self.child.wait()).await {
+            Ok(status) => status?,
+            Err(_) => return Err(self.timed_out()),
+        };
+        let stderr = (&mut self.stderr).await.unwrap_or_default();
+        let stderr = String::from_utf8_lossy(&stderr).to_string();
+        if !status.success() {
+            warn!("yt-dlp exited with {}. {}", status, stderr.trim());
+        }
+        Ok((status, stderr))
+    }
+
+    fn timed_out(&self) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("yt-dlp printed nothing for {}s", self.timeout.as_secs()),
+        )
+    }
+}
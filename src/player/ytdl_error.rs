@@ -0,0 +1,312 @@
+use songbird::input::error::Error as InputError;
+use std::fmt::Display;
+use std::io::{BufRead, BufReader, ErrorKind, Read};
+
+/// Why yt-dlp could not give us a track, read from its exit code and stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YtdlError {
+    /// The video was removed, or never existed.
+    Unavailable,
+    /// The video is private.
+    Private,
+    /// The site wants a signed in adult.
+    AgeRestricted,
+    /// The video is blocked where the bot runs.
+    RegionBlocked,
+    /// A stream or premiere that hasn't started yet.
+    LiveNotStarted,
+    /// The site is throttling the bot, or asking it to prove it isn't one.
+    RateLimited,
+    /// yt-dlp doesn't know the site.
+    UnsupportedUrl,
+    /// The site could not be reached, or the lookup timed out.
+    Network,
+    /// yt-dlp failed in a way none of the above matched.
+    Unknown,
+}
+
+impl YtdlError {
+    /// Reads the error yt-dlp printed. Only the `ERROR:` lines are looked at when there are any,
+    /// so warnings don't get mistaken for the cause.
+    pub fn parse(exit_code: Option<i32>, stderr: &str) -> YtdlError {
+        let error_lines: Vec<&str> = stderr
+            .lines()
+            .filter(|line| line.trim_start().starts_with("ERROR:"))
+            .collect();
+        let text = if error_lines.is_empty() {
+            stderr.to_lowercase()
+        } else {
+            error_lines.join("\n").to_lowercase()
+        };
+        let has = |needles: &[&str]| needles.iter().any(|needle| text.contains(needle));
+
+        // The more specific messages come first, several of them also say "unavailable".
+        if has(&["unsupported url", "no suitable extractor"]) {
+            YtdlError::UnsupportedUrl
+        } else if has(&[
+            "not made this video available in your country",
+            "not available in your country",
+            "geo restriction",
+            "geo-restricted",
+            "blocked it in your country",
+        ]) {
+            YtdlError::RegionBlocked
+        } else if has(&["private video", "this video is private"]) {
+            YtdlError::Private
+        } else if has(&[
+            "confirm your age",
+            "age-restricted",
+            "age restricted",
+            "inappropriate for some users",
+        ]) {
+            YtdlError::AgeRestricted
+        } else if has(&[
+            "live event will begin",
+            "premieres in",
+            "this live stream has not started",
+            "is upcoming",
+        ]) {
+            YtdlError::LiveNotStarted
+        } else if has(&[
+            "http error 429",
+            "too many requests",
+            "not a bot",
+            "rate-limited",
+            "rate limited",
+        ]) {
+            YtdlError::RateLimited
+        } else if has(&[
+            "video unavailable",
+            "has been removed",
+            "no longer available",
+            "does not exist",
+            "http error 404",
+            "this video is unavailable",
+        ]) {
+            YtdlError::Unavailable
+        } else if has(&[
+            "unable to download",
+            "urlopen error",
+            "temporary failure in name resolution",
+            "connection reset",
+            "connection refused",
+            "timed out",
+            "http error 5",
+            "transporterror",
+        ]) {
+            YtdlError::Network
+        } else if exit_code.is_none() {
+            // Killed by a signal, nothing to go by but that it didn't finish.
+            YtdlError::Network
+        } else {
+            YtdlError::Unknown
+        }
+    }
+
+    /// Reads the stderr of a streaming yt-dlp until it closes. Only `ERROR:` lines are kept, a
+    /// stream that retries a lot prints plenty of warnings. `None` if yt-dlp reported no error.
+    pub fn from_stream(stderr: impl Read) -> Option<YtdlError> {
+        let error_lines: Vec<String> = BufReader::new(stderr)
+            .split(b'\n')
+            .map_while(Result::ok)
+            .map(|line| String::from_utf8_lossy(&line).into_owned())
+            .filter(|line| line.trim_start().starts_with("ERROR:"))
+            .collect();
+        if error_lines.is_empty() {
+            return None;
+        }
+        Some(YtdlError::parse(Some(1), &error_lines.join("\n")))
+    }
+
+    /// The yt-dlp failure behind an input error, if it came from yt-dlp at all.
+    pub fn from_input_error(err: &InputError) -> Option<YtdlError> {
+        match err {
+            InputError::YouTubeDlRun(output) => Some(YtdlError::parse(
+                output.status.code(),
+                &String::from_utf8_lossy(&output.stderr),
+            )),
+            InputError::Io(err) if err.kind() == ErrorKind::TimedOut => Some(YtdlError::Network),
+            _ => None,
+        }
+    }
+
+    /// Whether trying the same track again might work.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, YtdlError::Network | YtdlError::RateLimited)
+    }
+
+    /// What to tell the user who asked for the track.
+    pub fn user_message(&self) -> &'static str {
+        match self {
+            YtdlError::Unavailable => "That video is unavailable, it may have been removed.",
+            YtdlError::Private => "That video is private.",
+            YtdlError::AgeRestricted => {
+                "That video is age restricted and can't be played without signing in."
+            }
+            YtdlError::RegionBlocked => "That video is not available in the bot's region.",
+            YtdlError::LiveNotStarted => {
+                "That stream hasn't started yet, try again once it is live."
+            }
+            YtdlError::RateLimited => {
+                "The site is limiting the bot's requests right now, try again in a few minutes."
+            }
+            YtdlError::UnsupportedUrl => "That link isn't supported, try a video link or a search.",
+            YtdlError::Network => "The site could not be reached, try again in a moment.",
+            YtdlError::Unknown => "Failed to get the track.",
+        }
+    }
+}
+
+impl Display for YtdlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            YtdlError::Unavailable => write!(f, "the video is unavailable"),
+            YtdlError::Private => write!(f, "the video is private"),
+            YtdlError::AgeRestricted => write!(f, "the video is age restricted"),
+            YtdlError::RegionBlocked => write!(f, "the video is blocked in this region"),
+            YtdlError::LiveNotStarted => write!(f, "the stream hasn't started yet"),
+            YtdlError::RateLimited => write!(f, "the site is rate limiting the bot"),
+            YtdlError::UnsupportedUrl => write!(f, "the link is not supported"),
+            YtdlError::Network => write!(f, "the source could not be reached"),
+            YtdlError::Unknown => write!(f, "the source could not be read"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!("../../tests/fixtures/ytdl/", $name, ".stderr"))
+        };
+    }
+
+    #[test]
+    fn removed_video_is_unavailable() {
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("removed")),
+            YtdlError::Unavailable
+        );
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("terminated_account")),
+            YtdlError::Unavailable
+        );
+    }
+
+    #[test]
+    fn private_video() {
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("private")),
+            YtdlError::Private
+        );
+    }
+
+    #[test]
+    fn age_restricted_video() {
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("age_restricted")),
+            YtdlError::AgeRestricted
+        );
+    }
+
+    #[test]
+    fn region_blocked_video() {
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("region_blocked")),
+            YtdlError::RegionBlocked
+        );
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("geo_restricted")),
+            YtdlError::RegionBlocked
+        );
+    }
+
+    #[test]
+    fn upcoming_stream() {
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("live_not_started")),
+            YtdlError::LiveNotStarted
+        );
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("premiere")),
+            YtdlError::LiveNotStarted
+        );
+    }
+
+    #[test]
+    fn rate_limited() {
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("too_many_requests")),
+            YtdlError::RateLimited
+        );
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("bot_check")),
+            YtdlError::RateLimited
+        );
+    }
+
+    #[test]
+    fn unsupported_url() {
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("unsupported_url")),
+            YtdlError::UnsupportedUrl
+        );
+    }
+
+    #[test]
+    fn network_failure() {
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("name_resolution")),
+            YtdlError::Network
+        );
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("server_error")),
+            YtdlError::Network
+        );
+    }
+
+    #[test]
+    fn warnings_are_not_the_cause() {
+        assert_eq!(
+            YtdlError::parse(Some(1), fixture!("warning_then_private")),
+            YtdlError::Private
+        );
+    }
+
+    #[test]
+    fn unrecognised_errors() {
+        assert_eq!(
+            YtdlError::parse(Some(2), "Usage: yt-dlp [OPTIONS] URL [URL...]\n"),
+            YtdlError::Unknown
+        );
+        assert_eq!(YtdlError::parse(None, ""), YtdlError::Network);
+    }
+
+    #[test]
+    fn network_and_rate_limit_failures_are_transient() {
+        assert!(YtdlError::Network.is_transient());
+        assert!(YtdlError::RateLimited.is_transient());
+        assert!(!YtdlError::Private.is_transient());
+        assert!(!YtdlError::Unavailable.is_transient());
+    }
+
+    #[test]
+    fn stream_errors_are_read_from_stderr() {
+        assert_eq!(
+            YtdlError::from_stream(fixture!("warning_then_private").as_bytes()),
+            Some(YtdlError::Private)
+        );
+        assert_eq!(
+            YtdlError::from_stream(fixture!("server_error").as_bytes()),
+            Some(YtdlError::Network)
+        );
+    }
+
+    #[test]
+    fn warnings_alone_are_no_stream_error() {
+        let stderr = "WARNING: [youtube] Falling back to generic n function search\n";
+        assert_eq!(YtdlError::from_stream(stderr.as_bytes()), None);
+    }
+}
@@ -1,16 +1,22 @@
-use crate::dbmodels::guild::Guild;
-use crate::mongo_conn::{get_collection, get_db};
+use crate::config::bot_config;
+use crate::dbmodels::guild::{default_channel_id, default_idle_timeout, AnnounceMode, Guild};
+use crate::dbmodels::history::HistoryEntry;
+use crate::dbmodels::liked::LikedTrack;
+use crate::dbmodels::metadata_cache::CachedMetadata;
+use crate::mongo_conn::Db;
 use mongodb::bson::doc;
 use mongodb::options::IndexOptions;
 use mongodb::*;
+use serenity::futures::TryStreamExt;
 use serenity::prelude::*;
+use std::time::Duration;
 use tracing::*;
 
-#[instrument(skip(ctx, client))]
-pub async fn insert_guilds(ctx: &Context, client: &mongodb::Client) -> Result<(), String> {
-    let db = get_db(client, "botdb").await;
-    let col: Collection<Guild> = get_collection(&db, "guilds", None).await;
+#[instrument(skip(ctx, db))]
+pub async fn insert_guilds(ctx: &Context, db: &Db) -> Result<(), String> {
+    let col: Collection<Guild> = db.guilds();
     let guilds = ctx.cache.guilds();
+    let default_volume = bot_config(ctx).await.player.default_volume;
     for guild in guilds {
         info!("Inserting ({}) into MongoDB", guild.0);
         let res = col
@@ -20,7 +26,16 @@ pub async fn insert_guilds(ctx: &Context, client: &mongodb::Client) -> Result<()
                     mod_channel_ID: "0".to_string(),
                     mod_role_ID: "0".to_string(),
                     prefix_string: "~".to_string(),
-                    volume: 0.7
+                    volume: default_volume,
+                    idle_timeout: default_idle_timeout(),
+                    always_on: false,
+                    always_on_channel_ID: default_channel_id(),
+                    saved_queue: vec![],
+                    announce_channel_ID: default_channel_id(),
+                    announce_mode: AnnounceMode::Off,
+                    announce_tidy: false,
+                    sponsorblock_categories: vec![],
+                    audio_cache: false,
                 },
                 None,
             )
@@ -46,3 +61,111 @@ pub async fn insert_guilds(ctx: &Context, client: &mongodb::Client) -> Result<()
     }
     Ok(())
 }
+
+/// The TTL index that expires old history entries.
+const HISTORY_TTL_INDEX: &str = "started_at_ttl";
+
+/// Creates the indexes for the play history, including the TTL index that expires old entries
+/// after `retention`. An existing TTL index gets the new expiry when the retention changed.
+#[instrument(skip(db))]
+pub async fn create_history_indexes(db: &Db, retention: Duration) -> Result<(), String> {
+    let col: Collection<HistoryEntry> = db.history();
+
+    let guild_model = IndexModel::builder()
+        .keys(doc! {"guild_ID": 1, "started_at": -1})
+        .build();
+    info!("Creating history indexes");
+    if let Err(err) = col.create_index(guild_model, None).await {
+        return Err(format!("{:?}", err));
+    }
+
+    // The collection exists now, the index above created it.
+    let indexes: Vec<IndexModel> = match col.list_indexes(None).await {
+        Ok(cursor) => cursor
+            .try_collect()
+            .await
+            .map_err(|err| format!("{:?}", err))?,
+        Err(err) => return Err(format!("{:?}", err)),
+    };
+    let current_expiry = indexes
+        .iter()
+        .filter_map(|index| index.options.as_ref())
+        .find(|options| options.name.as_deref() == Some(HISTORY_TTL_INDEX))
+        .map(|options| options.expire_after);
+
+    match current_expiry {
+        Some(expiry) if expiry == Some(retention) => Ok(()),
+        Some(_) => {
+            info!(
+                "Changing the history retention to {} days",
+                retention.as_secs() / (24 * 60 * 60)
+            );
+            db.set_expiry("history", HISTORY_TTL_INDEX, retention)
+                .await
+                .map_err(|err| format!("{:?}", err))
+        }
+        None => {
+            let ttl_model = IndexModel::builder()
+                .keys(doc! {"started_at": 1})
+                .options(
+                    IndexOptions::builder()
+                        .name(HISTORY_TTL_INDEX.to_string())
+                        .expire_after(retention)
+                        .build(),
+                )
+                .build();
+            info!(
+                "Creating the history TTL index with a retention of {} days",
+                retention.as_secs() / (24 * 60 * 60)
+            );
+            col.create_index(ttl_model, None)
+                .await
+                .map(|_| ())
+                .map_err(|err| format!("{:?}", err))
+        }
+    }
+}
+
+/// Makes sure a user can only like the same track once.
+#[instrument(skip(db))]
+pub async fn create_liked_indexes(db: &Db) -> Result<(), String> {
+    let col: Collection<LikedTrack> = db.liked();
+
+    let user_model = IndexModel::builder()
+        .keys(doc! {"user_ID": 1, "url": 1})
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+
+    info!("Creating liked track indexes");
+    if let Err(err) = col.create_index(user_model, None).await {
+        return Err(format!("{:?}", err));
+    }
+    Ok(())
+}
+
+/// Makes the metadata cache keys unique and drops entries that weren't refreshed in 30 days.
+/// Entries still in use are refreshed long before that.
+#[instrument(skip(db))]
+pub async fn create_metadata_cache_indexes(db: &Db) -> Result<(), String> {
+    let col: Collection<CachedMetadata> = db.metadata_cache();
+
+    let key_model = IndexModel::builder()
+        .keys(doc! {"key": 1})
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    let ttl_model = IndexModel::builder()
+        .keys(doc! {"fetched_at": 1})
+        .options(
+            IndexOptions::builder()
+                .name("fetched_at_ttl".to_string())
+                .expire_after(Duration::from_secs(30 * 24 * 60 * 60))
+                .build(),
+        )
+        .build();
+
+    info!("Creating metadata cache indexes");
+    if let Err(err) = col.create_indexes([key_model, ttl_model], None).await {
+        return Err(format!("{:?}", err));
+    }
+    Ok(())
+}